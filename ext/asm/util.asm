@@ -4,3 +4,7 @@ _nop::
 _halt::
 	halt
 	ret
+
+_stop::
+	stop
+	ret
@@ -44,14 +44,15 @@ impl BuildStep for AstGrep {
             .collect::<Result<Vec<_>, io::Error>>()?;
         rules.sort_by_key(|e| e.file_name());
 
+        let mut c_paths = vec![format!("{}/out.c", dir.out)];
+        c_paths.append(&mut dir.get_banked_c_paths());
+
         for rule in rules {
-            if let Err(err) = Self::scan(
-                &rule.path().to_str().unwrap(),
-                format!("{}/out.c", dir.out).as_str(),
-                &bar,
-            ) {
-                bar.finish_with_message(format!("{}", "Ast-Grep Run Failed".red()));
-                return Err(err);
+            for c_path in &c_paths {
+                if let Err(err) = Self::scan(rule.path().to_str().unwrap(), c_path, bar) {
+                    bar.finish_with_message(format!("{}", "Ast-Grep Run Failed".red()));
+                    return Err(err);
+                }
             }
         }
 
@@ -0,0 +1,260 @@
+use std::ops::Range;
+
+use tree_sitter::{Node, Parser};
+
+/// Suffix of C identifiers of banked functions and statics.
+///
+/// Rust can't put attributes on symbols, so they are written in the symbol
+/// name (`#[export_name = "name __banked"]`), and LLVM-CBE mangles the space
+/// into `_AC_`.
+pub const BANKED_MARKER: &str = "_AC___banked";
+
+/// Splits banked functions and statics out of `code`, one C file each.
+///
+/// Each file is autobanked (`#pragma bank 255`), and starts with the type
+/// definitions and function declarations of `code`. Files of functions also
+/// get the inline functions of `code`.
+/// Global variables of `code` are declared `extern` in it, so `static` is
+/// removed from the definitions and declarations of `code`.
+///
+/// A banked static is made `const` so that it stays in ROM, and its bank is
+/// given by the `__bank_<name>` symbol. (As `BANKREF` of GBDK)
+pub fn split_banked(code: &mut String) -> Vec<String> {
+    if !code.contains(BANKED_MARKER) {
+        return Vec::new();
+    }
+    let source = code.clone();
+    let mut parser = Parser::new();
+    parser
+        .set_language(&tree_sitter_c::LANGUAGE.into())
+        .unwrap();
+    let tree = parser.parse(source.as_bytes(), None).unwrap();
+
+    let mut edits: Vec<(Range<usize>, String)> = Vec::new();
+    // Declarations for every banked file, and inline functions for banked functions.
+    let mut declarations = String::new();
+    let mut inline_functions = String::new();
+    let mut functions = Vec::new();
+    let mut statics = Vec::new();
+
+    let root = tree.root_node();
+    let mut cursor = root.walk();
+    let mut last_end = 0;
+    for node in root.children(&mut cursor) {
+        // Keep the whitespace between nodes.
+        declarations.push_str(&source[last_end..node.start_byte()]);
+        last_end = node.end_byte();
+
+        let text = &source[node.byte_range()];
+        let name = declarator_name(node, &source).unwrap_or("");
+        match node.kind() {
+            "function_definition" => {
+                if name.contains(BANKED_MARKER) {
+                    functions.push(text.to_string());
+                    edits.push((node.byte_range(), String::new()));
+                } else if has_specifier(node, &source, is_inline) {
+                    inline_functions.push_str(text);
+                    inline_functions.push('\n');
+                } else if let Some(range) = specifier_range(node, &source, "static") {
+                    edits.push((range, String::new()));
+                }
+            }
+            "declaration"
+                if is_variable(node) && !has_specifier(node, &source, |s| s == "extern") =>
+            {
+                let banked = name.contains(BANKED_MARKER);
+                let declaration = extern_declaration(node, &source, banked);
+                if banked {
+                    add_banked_static(&mut statics, node, &source);
+                    edits.push((node.byte_range(), declaration.clone()));
+                } else if let Some(range) = specifier_range(node, &source, "static") {
+                    edits.push((range, String::new()));
+                }
+                declarations.push_str(&declaration);
+            }
+            "declaration" if !has_specifier(node, &source, is_inline) => {
+                match specifier_range(node, &source, "static") {
+                    Some(range) => {
+                        edits.push((range.clone(), String::new()));
+                        declarations.push_str(&replace_relative(
+                            node,
+                            text,
+                            &[(range, String::new())],
+                        ));
+                    }
+                    None => declarations.push_str(text),
+                }
+            }
+            _ => declarations.push_str(text),
+        }
+    }
+
+    let mut files: Vec<String> = functions
+        .iter()
+        .map(|function| {
+            format!(
+                "#pragma bank 255\n{}\n{}\n{}\n",
+                declarations, inline_functions, function
+            )
+        })
+        .collect();
+    for (name, definition) in &statics {
+        let plain = name.replace(BANKED_MARKER, "");
+        files.push(format!(
+            "#pragma bank 255\n{}\n{}\nconst void __at(255) __bank_{};\n",
+            declarations, definition, plain
+        ));
+    }
+
+    edits.sort_by_key(|(range, _)| range.start);
+    for (range, text) in edits.into_iter().rev() {
+        code.replace_range(range, &text);
+    }
+
+    // Banked statics keep their plain name.
+    for (name, _) in &statics {
+        let plain = name.replace(BANKED_MARKER, "");
+        *code = code.replace(name.as_str(), &plain);
+        for file in files.iter_mut() {
+            *file = file.replace(name.as_str(), &plain);
+        }
+    }
+    files
+}
+
+fn is_inline(specifier: &str) -> bool {
+    matches!(
+        specifier,
+        "inline" | "__inline" | "__inline__" | "__forceinline"
+    )
+}
+
+fn has_specifier(node: Node, source: &str, f: impl Fn(&str) -> bool) -> bool {
+    let mut cursor = node.walk();
+    let found = node
+        .children(&mut cursor)
+        .any(|child| child.kind() == "storage_class_specifier" && f(&source[child.byte_range()]));
+    found
+}
+
+/// Range of a storage class specifier, with the following spaces.
+fn specifier_range(node: Node, source: &str, specifier: &str) -> Option<Range<usize>> {
+    let mut cursor = node.walk();
+    let found = node
+        .children(&mut cursor)
+        .find(|child| {
+            child.kind() == "storage_class_specifier" && &source[child.byte_range()] == specifier
+        })
+        .map(|child| {
+            let spaces = source[child.end_byte()..node.end_byte()]
+                .bytes()
+                .take_while(|b| *b == b' ')
+                .count();
+            child.start_byte()..child.end_byte() + spaces
+        });
+    found
+}
+
+/// Innermost declarator of a declaration or definition, and whether it is a
+/// function.
+fn innermost_declarator(node: Node) -> Option<(Node, bool)> {
+    let mut declarator = node.child_by_field_name("declarator")?;
+    let mut is_function = false;
+    loop {
+        match declarator.kind() {
+            "identifier" => return Some((declarator, is_function)),
+            "function_declarator" => {
+                let inner = declarator.child_by_field_name("declarator")?;
+                is_function = inner.kind() == "identifier";
+                declarator = inner;
+            }
+            "parenthesized_declarator" => declarator = declarator.named_child(0)?,
+            _ => declarator = declarator.child_by_field_name("declarator")?,
+        }
+    }
+}
+
+fn declarator_name<'a>(node: Node, source: &'a str) -> Option<&'a str> {
+    innermost_declarator(node).map(|(identifier, _)| &source[identifier.byte_range()])
+}
+
+/// Whether the declaration declares a variable, not a type or a function.
+fn is_variable(node: Node) -> bool {
+    matches!(innermost_declarator(node), Some((_, false)))
+}
+
+/// Replaces ranges of `source` inside `node`, given in absolute offsets.
+fn replace_relative(node: Node, text: &str, edits: &[(Range<usize>, String)]) -> String {
+    let mut text = text.to_string();
+    let mut edits = edits.to_vec();
+    edits.sort_by_key(|(range, _)| range.start);
+    for (range, replacement) in edits.into_iter().rev() {
+        let start = range.start - node.start_byte();
+        let end = range.end - node.start_byte();
+        text.replace_range(start..end, &replacement);
+    }
+    text
+}
+
+fn is_const(node: Node, source: &str) -> bool {
+    let mut cursor = node.walk();
+    let found = node
+        .children(&mut cursor)
+        .any(|child| child.kind() == "type_qualifier" && &source[child.byte_range()] == "const");
+    found
+}
+
+/// `extern` declaration of a variable definition.
+fn extern_declaration(node: Node, source: &str, banked: bool) -> String {
+    let mut edits = Vec::new();
+    if let Some(range) = specifier_range(node, source, "static") {
+        edits.push((range, String::new()));
+    }
+    let mut cursor = node.walk();
+    for child in node.children_by_field_name("declarator", &mut cursor) {
+        if child.kind() == "init_declarator" {
+            if let Some(declarator) = child.child_by_field_name("declarator") {
+                edits.push((
+                    child.byte_range(),
+                    source[declarator.byte_range()].to_string(),
+                ));
+            }
+        }
+    }
+    let declaration = replace_relative(node, &source[node.byte_range()], &edits);
+    if banked && !is_const(node, source) {
+        format!("extern const {}", declaration)
+    } else {
+        format!("extern {}", declaration)
+    }
+}
+
+/// Adds the definition of a banked static. A variable can be declared
+/// without an initializer before its definition, which replaces it.
+fn add_banked_static(statics: &mut Vec<(String, String)>, node: Node, source: &str) {
+    let (name, definition) = definition(node, source);
+    let mut cursor = node.walk();
+    let initialized = node
+        .children_by_field_name("declarator", &mut cursor)
+        .any(|child| child.kind() == "init_declarator");
+    match statics.iter_mut().find(|(other, _)| *other == name) {
+        Some(existing) if initialized => existing.1 = definition,
+        Some(_) => {}
+        None => statics.push((name, definition)),
+    }
+}
+
+/// Name and `const` definition of a banked static.
+fn definition(node: Node, source: &str) -> (String, String) {
+    let name = declarator_name(node, source).unwrap_or("").to_string();
+    let mut edits = Vec::new();
+    if let Some(range) = specifier_range(node, source, "static") {
+        edits.push((range, String::new()));
+    }
+    let definition = replace_relative(node, &source[node.byte_range()], &edits);
+    if is_const(node, source) {
+        (name, definition)
+    } else {
+        (name, format!("const {}", definition))
+    }
+}
@@ -0,0 +1,374 @@
+use std::fs;
+
+use toml::{Table, Value};
+
+use crate::BuildStepError;
+
+/// GameBoy Color support of the ROM, written in the cartridge header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CgbMode {
+    /// Runs on DMG, and in DMG compatibility mode on CGB.
+    #[default]
+    None,
+    /// Runs on DMG, and in color mode on CGB.
+    Compatible,
+    /// Runs only on CGB, in color mode.
+    Only,
+}
+
+impl CgbMode {
+    /// makebin flag of the mode, passed through lcc.
+    pub fn makebin_flag(self) -> Option<&'static str> {
+        match self {
+            CgbMode::None => None,
+            CgbMode::Compatible => Some("-Wm-yc"),
+            CgbMode::Only => Some("-Wm-yC"),
+        }
+    }
+}
+
+/// Memory bank controller of the cartridge.
+///
+/// It must match the `mbc1`, `mbc3` or `mbc5` feature of the library.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Mbc {
+    /// 32 KiB ROM without a controller.
+    #[default]
+    None,
+    /// MBC1, up to 512 KiB ROM.
+    Mbc1,
+    /// MBC3, up to 2 MiB ROM.
+    Mbc3,
+    /// MBC5, up to 8 MiB ROM.
+    Mbc5,
+}
+
+impl Mbc {
+    /// Name of the controller in the configuration.
+    fn name(self) -> &'static str {
+        match self {
+            Mbc::None => "none",
+            Mbc::Mbc1 => "mbc1",
+            Mbc::Mbc3 => "mbc3",
+            Mbc::Mbc5 => "mbc5",
+        }
+    }
+
+    /// Cartridge type in the header, from the controller and its hardware.
+    fn cartridge_type(self, ram: bool, battery: bool, rtc: bool) -> Result<u8, BuildStepError> {
+        if rtc && self != Mbc::Mbc3 {
+            return Err(config_error("`rtc` needs `mbc = \"mbc3\"`"));
+        }
+        if rtc && !battery {
+            return Err(config_error("`rtc` needs `battery = true`"));
+        }
+        if battery && !ram && !rtc {
+            return Err(config_error("`battery` needs `ram-size`"));
+        }
+        let cartridge_type = match (self, ram, battery, rtc) {
+            (Mbc::None, false, _, _) => 0x00,
+            (Mbc::None, true, false, _) => 0x08,
+            (Mbc::None, true, true, _) => 0x09,
+            (Mbc::Mbc1, false, _, _) => 0x01,
+            (Mbc::Mbc1, true, false, _) => 0x02,
+            (Mbc::Mbc1, true, true, _) => 0x03,
+            (Mbc::Mbc3, false, _, true) => 0x0F,
+            (Mbc::Mbc3, true, _, true) => 0x10,
+            (Mbc::Mbc3, false, _, false) => 0x11,
+            (Mbc::Mbc3, true, false, false) => 0x12,
+            (Mbc::Mbc3, true, true, false) => 0x13,
+            (Mbc::Mbc5, false, _, _) => 0x19,
+            (Mbc::Mbc5, true, false, _) => 0x1A,
+            (Mbc::Mbc5, true, true, _) => 0x1B,
+        };
+        Ok(cartridge_type)
+    }
+
+    /// Largest ROM size in KiB.
+    ///
+    /// MBC1 is limited to the banks the library can switch to.
+    fn max_rom_size(self) -> u32 {
+        match self {
+            Mbc::None => 32,
+            Mbc::Mbc1 => 512,
+            Mbc::Mbc3 => 2048,
+            Mbc::Mbc5 => 8192,
+        }
+    }
+
+    /// Largest RAM size in KiB.
+    fn max_ram_size(self) -> u32 {
+        match self {
+            Mbc::None => 8,
+            Mbc::Mbc1 | Mbc::Mbc3 => 32,
+            Mbc::Mbc5 => 128,
+        }
+    }
+
+    /// bankpack flags of the controller, passed through lcc.
+    ///
+    /// MBC1 is limited to the banks the library can switch to. Banked calls
+    /// of SDCC only have an 8-bit bank, so MBC5 is limited to 255 banks by
+    /// bankpack.
+    pub fn bankpack_flags(self) -> Vec<&'static str> {
+        match self {
+            Mbc::None => Vec::new(),
+            Mbc::Mbc1 => vec!["-Wb-mbc=1", "-Wb-max=31"],
+            Mbc::Mbc3 => vec!["-Wb-mbc=3"],
+            Mbc::Mbc5 => vec!["-Wb-mbc=5"],
+        }
+    }
+}
+
+/// Licensee code in the cartridge header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Licensee {
+    /// Old licensee code, a byte.
+    Old(u8),
+    /// New licensee code, two ASCII characters.
+    New(String),
+}
+
+/// Project configuration in `[package.metadata.rust-gb]` of `Cargo.toml`.
+///
+/// ```toml
+/// [package.metadata.rust-gb]
+/// title = "MY GAME"    # Up to 16 ASCII characters, 15 with `cgb`
+/// licensee = "01"      # New licensee code, or an integer for the old one
+/// version = 0          # Mask ROM version number
+/// cgb = "compatible"   # "none", "compatible" or "only"
+/// sgb = true           # Super GameBoy support
+/// mbc = "mbc5"         # "none", "mbc1", "mbc3" or "mbc5"
+/// rom-size = "auto"    # KiB, from 32 to 8192, or "auto"
+/// ram-size = 32        # KiB, 0, 8, 32, 64 or 128
+/// battery = true       # Battery-backed RAM (or RTC)
+/// rtc = false          # Real time clock of MBC3
+/// ```
+///
+/// The cartridge type in the header is made from `mbc`, `ram-size`,
+/// `battery` and `rtc`.
+#[derive(Debug, Default)]
+pub struct Config {
+    pub title: Option<String>,
+    pub licensee: Option<Licensee>,
+    pub version: u8,
+    pub cgb: CgbMode,
+    pub sgb: bool,
+    pub mbc: Mbc,
+    /// ROM size in KiB, calculated from the used banks if `None`.
+    pub rom_size: Option<u32>,
+    /// RAM size in KiB.
+    pub ram_size: u32,
+    pub battery: bool,
+    pub rtc: bool,
+    /// Cartridge type in the header, checked by [`Config::load`].
+    cartridge_type: u8,
+}
+
+impl Config {
+    pub fn load(root: &str) -> Result<Config, BuildStepError> {
+        let manifest = fs::read_to_string(format!("{}/Cargo.toml", root))?;
+        let manifest: Table = manifest
+            .parse()
+            .map_err(|e: toml::de::Error| BuildStepError::ConfigError(e.message().to_string()))?;
+
+        let metadata = manifest
+            .get("package")
+            .and_then(|package| package.get("metadata"))
+            .and_then(|metadata| metadata.get("rust-gb"));
+        let Some(metadata) = metadata else {
+            return Ok(Config::default());
+        };
+        let Some(metadata) = metadata.as_table() else {
+            return Err(config_error("package.metadata.rust-gb is not a table"));
+        };
+
+        let mut config = Config::default();
+        for (key, value) in metadata {
+            match key.as_str() {
+                "title" => config.title = Some(Self::parse_title(value)?),
+                "licensee" => config.licensee = Some(Self::parse_licensee(value)?),
+                "version" => config.version = Self::parse_byte(key, value)?,
+                "cgb" => config.cgb = Self::parse_cgb(value)?,
+                "sgb" => config.sgb = Self::parse_bool(key, value)?,
+                "mbc" => config.mbc = Self::parse_mbc(value)?,
+                "rom-size" => config.rom_size = Self::parse_rom_size(value)?,
+                "ram-size" => config.ram_size = Self::parse_ram_size(value)?,
+                "battery" => config.battery = Self::parse_bool(key, value)?,
+                "rtc" => config.rtc = Self::parse_bool(key, value)?,
+                _ => {
+                    return Err(config_error(&format!(
+                        "unknown key `{}` in package.metadata.rust-gb",
+                        key
+                    )))
+                }
+            }
+        }
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Check the combination of the options, and make the cartridge type.
+    fn validate(&mut self) -> Result<(), BuildStepError> {
+        self.cartridge_type = self
+            .mbc
+            .cartridge_type(self.ram_size > 0, self.battery, self.rtc)?;
+
+        if let Some(rom_size) = self.rom_size {
+            if rom_size > self.mbc.max_rom_size() {
+                return Err(config_error(&format!(
+                    "`rom-size` must be at most {} KiB with `mbc = \"{}\"`",
+                    self.mbc.max_rom_size(),
+                    self.mbc.name()
+                )));
+            }
+        }
+        if self.ram_size > self.mbc.max_ram_size() {
+            return Err(config_error(&format!(
+                "`ram-size` must be at most {} KiB with `mbc = \"{}\"`",
+                self.mbc.max_ram_size(),
+                self.mbc.name()
+            )));
+        }
+        // The last byte of the title is the CGB flag.
+        if let Some(title) = &self.title {
+            if self.cgb != CgbMode::None && title.len() > 15 {
+                return Err(config_error(
+                    "`title` must be at most 15 characters with `cgb`",
+                ));
+            }
+        }
+        // The SGB functions are enabled only with the old licensee code 0x33.
+        if let Some(Licensee::Old(code)) = self.licensee {
+            if self.sgb && code != 0x33 {
+                return Err(config_error(
+                    "`sgb` needs the new licensee code, or the old one 0x33",
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// makebin flags of the cartridge header, passed through lcc.
+    pub fn makebin_flags(&self) -> Vec<String> {
+        let mut flags = Vec::new();
+        if let Some(title) = &self.title {
+            flags.push(format!("-Wm-yn{}", title));
+        }
+        match &self.licensee {
+            Some(Licensee::Old(code)) => flags.push(format!("-Wm-yl0x{:02X}", code)),
+            Some(Licensee::New(code)) => flags.push(format!("-Wm-yk{}", code)),
+            None => {}
+        }
+        if self.version != 0 {
+            flags.push(format!("-Wm-yp0x14C=0x{:02X}", self.version));
+        }
+        // CGB functions of GBDK are in `gb.lib`, so only the header flag is needed.
+        if let Some(flag) = self.cgb.makebin_flag() {
+            flags.push(flag.to_string());
+        }
+        // Sets the SGB flag, and the old licensee code to 0x33 as the SGB requires.
+        if self.sgb {
+            flags.push("-Wm-ys".to_string());
+        }
+        if self.cartridge_type != 0 {
+            flags.push(format!("-Wm-yt0x{:02X}", self.cartridge_type));
+        }
+        // Without a size, it is calculated from the used banks.
+        match self.rom_size {
+            Some(rom_size) => flags.push(format!("-Wm-yo{}", rom_size / 16)),
+            None if self.mbc != Mbc::None => flags.push("-Wm-yoA".to_string()),
+            None => {}
+        }
+        if self.ram_size > 0 {
+            flags.push(format!("-Wm-ya{}", self.ram_size.div_ceil(8)));
+        }
+        flags
+    }
+
+    fn parse_bool(key: &str, value: &Value) -> Result<bool, BuildStepError> {
+        value
+            .as_bool()
+            .ok_or_else(|| config_error(&format!("`{}` must be a boolean", key)))
+    }
+
+    fn parse_byte(key: &str, value: &Value) -> Result<u8, BuildStepError> {
+        value
+            .as_integer()
+            .and_then(|value| u8::try_from(value).ok())
+            .ok_or_else(|| config_error(&format!("`{}` must be an integer from 0 to 255", key)))
+    }
+
+    fn parse_title(value: &Value) -> Result<String, BuildStepError> {
+        match value.as_str() {
+            Some(title)
+                if title.len() <= 16 && title.chars().all(|c| c.is_ascii_graphic() || c == ' ') =>
+            {
+                Ok(title.to_string())
+            }
+            _ => Err(config_error("`title` must be at most 16 ASCII characters")),
+        }
+    }
+
+    fn parse_licensee(value: &Value) -> Result<Licensee, BuildStepError> {
+        match value {
+            Value::String(code)
+                if code.len() == 2 && code.chars().all(|c| c.is_ascii_graphic()) =>
+            {
+                Ok(Licensee::New(code.clone()))
+            }
+            Value::Integer(code) => u8::try_from(*code)
+                .map(Licensee::Old)
+                .map_err(|_| config_error("old `licensee` code must be from 0 to 255")),
+            _ => Err(config_error(
+                "`licensee` must be 2 ASCII characters, or an integer for the old code",
+            )),
+        }
+    }
+
+    fn parse_rom_size(value: &Value) -> Result<Option<u32>, BuildStepError> {
+        match value {
+            Value::String(size) if size == "auto" => Ok(None),
+            Value::Integer(size @ 32..=8192) if (*size as u32).is_power_of_two() => {
+                Ok(Some(*size as u32))
+            }
+            _ => Err(config_error(
+                "`rom-size` must be \"auto\", or 32, 64, 128, ..., 8192 (KiB)",
+            )),
+        }
+    }
+
+    fn parse_ram_size(value: &Value) -> Result<u32, BuildStepError> {
+        match value.as_integer() {
+            Some(size @ (0 | 8 | 32 | 64 | 128)) => Ok(size as u32),
+            _ => Err(config_error("`ram-size` must be 0, 8, 32, 64 or 128 (KiB)")),
+        }
+    }
+
+    fn parse_cgb(value: &Value) -> Result<CgbMode, BuildStepError> {
+        match value.as_str() {
+            Some("none") => Ok(CgbMode::None),
+            Some("compatible") => Ok(CgbMode::Compatible),
+            Some("only") => Ok(CgbMode::Only),
+            _ => Err(config_error(
+                "`cgb` must be \"none\", \"compatible\" or \"only\"",
+            )),
+        }
+    }
+
+    fn parse_mbc(value: &Value) -> Result<Mbc, BuildStepError> {
+        match value.as_str() {
+            Some("none") => Ok(Mbc::None),
+            Some("mbc1") => Ok(Mbc::Mbc1),
+            Some("mbc3") => Ok(Mbc::Mbc3),
+            Some("mbc5") => Ok(Mbc::Mbc5),
+            _ => Err(config_error(
+                "`mbc` must be \"none\", \"mbc1\", \"mbc3\" or \"mbc5\"",
+            )),
+        }
+    }
+}
+
+fn config_error(message: &str) -> BuildStepError {
+    BuildStepError::ConfigError(message.to_string())
+}
@@ -3,7 +3,7 @@ use std::process::Command;
 use colored::Colorize;
 use indicatif::ProgressBar;
 
-use crate::{BuildStep, BuildStepError};
+use crate::{config::Mbc, BuildStep, BuildStepError};
 
 pub struct Lcc {}
 
@@ -12,12 +12,33 @@ impl BuildStep for Lcc {
         bar.set_message("GB ROM Linking...");
         let mut asm_path = dir.get_asm_paths();
 
-        let mut lcc_args: Vec<String> = vec![
-            "-msm83:gb".to_string(),
+        let mut lcc_args: Vec<String> = vec!["-msm83:gb".to_string()];
+
+        lcc_args.extend(dir.config.makebin_flags());
+
+        // Banked functions and statics are assigned to banks by bankpack.
+        if !dir.get_banked_c_paths().is_empty() {
+            if dir.config.mbc == Mbc::None {
+                bar.finish_with_message(format!("{}", "GB ROM Linking Failed".red()));
+                return Err(BuildStepError::ConfigError(
+                    "banked code needs the `mbc` option".to_string(),
+                ));
+            }
+            lcc_args.push("-autobank".to_string());
+            lcc_args.extend(
+                dir.config
+                    .mbc
+                    .bankpack_flags()
+                    .iter()
+                    .map(|f| f.to_string()),
+            );
+        }
+
+        lcc_args.extend([
             "-o".to_string(),
             format!("{}/out.gb", dir.out),
             format!("{}/out.asm", dir.out),
-        ];
+        ]);
 
         lcc_args.append(&mut asm_path);
 
@@ -1,11 +1,13 @@
 use ast_grep::AstGrep;
 use cargo::Cargo;
 use clap::{arg, command, Parser};
+use config::Config;
 use console::style;
 use core::str;
 use indicatif::{ProgressBar, ProgressStyle};
 use lcc::Lcc;
 use llvm::{LlvmCbe, LlvmLink};
+use music::Music;
 use std::{
     fs,
     os::unix::fs::PermissionsExt,
@@ -20,9 +22,12 @@ use sdcc::Sdcc;
 use treesitter::Treesitter;
 
 mod ast_grep;
+mod banking;
 mod cargo;
+mod config;
 mod lcc;
 mod llvm;
+mod music;
 mod sdcc;
 mod treesitter;
 
@@ -69,6 +74,7 @@ pub struct WorkingDirectory {
     pub root: String,
     pub ext: String,
     pub out: String,
+    pub config: Config,
 }
 
 impl WorkingDirectory {
@@ -77,6 +83,7 @@ impl WorkingDirectory {
             root: root.to_string(),
             ext: Self::create_ext_dir(root),
             out: Self::create_out_dir(root),
+            config: Self::load_config(root),
         }
     }
 
@@ -88,6 +95,16 @@ impl WorkingDirectory {
         format!("{}/out", root)
     }
 
+    fn load_config(root: &str) -> Config {
+        match Config::load(root) {
+            Ok(config) => config,
+            Err(e) => {
+                println!("{}", e.to_string().red());
+                process::exit(1);
+            }
+        }
+    }
+
     fn create_ext_dir(root: &str) -> String {
         let ext_dir = format!("{}/ext", root);
 
@@ -140,6 +157,41 @@ impl WorkingDirectory {
         }
     }
 
+    // find banked c files split from `out.c`, from `./out/banked`
+    pub fn get_banked_c_paths(&self) -> Vec<String> {
+        let Ok(entries) = fs::read_dir(format!("{}/banked", self.out)) else {
+            return Vec::new();
+        };
+        let mut banked_path: Vec<String> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path().to_string_lossy().to_string())
+            .filter(|path| path.ends_with(".c"))
+            .collect();
+        banked_path.sort();
+        banked_path
+    }
+
+    // find song files from `./music`
+    pub fn get_music_paths(&self) -> Vec<String> {
+        let music_path = Command::new("find")
+            .args([format!("{}/music", self.root).as_str(), "-name", "*.song"])
+            .output()
+            .unwrap()
+            .stdout;
+
+        let music_path = String::from_utf8(music_path).unwrap();
+        let music_path = music_path.trim().to_string();
+
+        if music_path.len() == 0 {
+            Vec::new()
+        } else {
+            let mut music_path: Vec<String> =
+                music_path.split("\n").map(|s| s.to_string()).collect();
+            music_path.sort();
+            music_path
+        }
+    }
+
     pub fn get_asm_paths(&self) -> Vec<String> {
         let asm_path = Command::new("find")
             .args([
@@ -170,6 +222,10 @@ pub enum BuildStepError {
     ChildExecutionFailed(String),
     #[error("{0} exited with status {1}")]
     ChildProcessFailed(String, ExitStatus),
+    #[error("{0}:{1}: {2}")]
+    MusicParseFailed(String, usize, String),
+    #[error("Config error: {0}")]
+    ConfigError(String),
     #[error("File error: {0}")]
     FileError(#[from] std::io::Error),
 }
@@ -208,7 +264,15 @@ fn main() {
     if build_from <= BuildChain::Rust {
         let bar = ProgressBar::new_spinner();
         bar.set_style(bar_style.clone());
-        bar.set_prefix("[1/7]");
+        bar.set_prefix("[1/8]");
+        bar.enable_steady_tick(Duration::from_millis(100));
+        if Music::run(&work_dir, &bar).is_err() {
+            process::exit(1)
+        }
+
+        let bar = ProgressBar::new_spinner();
+        bar.set_style(bar_style.clone());
+        bar.set_prefix("[2/8]");
         bar.enable_steady_tick(Duration::from_millis(100));
         if Cargo::run(&work_dir, &bar).is_err() {
             process::exit(1)
@@ -218,7 +282,7 @@ fn main() {
     if build_from <= BuildChain::LLVM {
         let bar = ProgressBar::new_spinner();
         bar.set_style(bar_style.clone());
-        bar.set_prefix("[2/7]");
+        bar.set_prefix("[3/8]");
         bar.enable_steady_tick(Duration::from_millis(100));
         if LlvmLink::run(&work_dir, &bar).is_err() {
             process::exit(1);
@@ -226,7 +290,7 @@ fn main() {
 
         let bar = ProgressBar::new_spinner();
         bar.set_style(bar_style.clone());
-        bar.set_prefix("[3/7]");
+        bar.set_prefix("[4/8]");
         bar.enable_steady_tick(Duration::from_millis(100));
         if LlvmCbe::run(&work_dir, &bar).is_err() {
             process::exit(1);
@@ -241,7 +305,7 @@ fn main() {
 
         let bar = ProgressBar::new_spinner();
         bar.set_style(bar_style.clone());
-        bar.set_prefix("[4/7]");
+        bar.set_prefix("[5/8]");
         bar.enable_steady_tick(Duration::from_millis(100));
         if Treesitter::run(&work_dir, &bar).is_err() {
             process::exit(1);
@@ -249,7 +313,7 @@ fn main() {
 
         let bar = ProgressBar::new_spinner();
         bar.set_style(bar_style.clone());
-        bar.set_prefix("[5/7]");
+        bar.set_prefix("[6/8]");
         bar.enable_steady_tick(Duration::from_millis(100));
         if AstGrep::run(&work_dir, &bar).is_err() {
             process::exit(1);
@@ -272,7 +336,7 @@ fn main() {
     if build_from <= BuildChain::C {
         let bar = ProgressBar::new_spinner();
         bar.set_style(bar_style.clone());
-        bar.set_prefix("[6/7]");
+        bar.set_prefix("[7/8]");
         bar.enable_steady_tick(Duration::from_millis(100));
         if Sdcc::run(&work_dir, &bar).is_err() {
             process::exit(1);
@@ -282,7 +346,7 @@ fn main() {
     if build_from <= BuildChain::ASM {
         let bar = ProgressBar::new_spinner();
         bar.set_style(bar_style.clone());
-        bar.set_prefix("[7/7]");
+        bar.set_prefix("[8/8]");
         bar.enable_steady_tick(Duration::from_millis(100));
         if Lcc::run(&work_dir, &bar).is_err() {
             process::exit(1);
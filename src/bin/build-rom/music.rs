@@ -0,0 +1,673 @@
+use std::{fs, path::Path};
+
+use colored::Colorize;
+use indicatif::ProgressBar;
+
+use crate::{BuildStep, BuildStepError};
+
+/// Converts the song files in `./music` into `gb::music::Song` statics.
+///
+/// Every `*.song` file becomes one `pub static` named after the file stem in
+/// upper case, written to `out/music.rs`. The game crate includes it with
+/// `include!(concat!(env!("CARGO_MANIFEST_DIR"), "/out/music.rs"))`.
+///
+/// # Song format
+/// ```text
+/// # Lines starting with '#' are comments
+/// speed 6
+///
+/// pulse 0 duty=50 volume=12 envelope=down:2
+/// wave 0 wave=triangle volume=100
+/// noise 0 volume=15 envelope=down:1 shift=4 width=7 divider=2 length=8
+///
+/// pattern intro
+/// C-4 00 ... | --- .. ... | C-3 00 ... | --- .. ...
+/// E-4 00 101 | --- .. ... | --- .. ... | C-4 00 ...
+/// OFF .. ... | --- .. ... | OFF .. ... | --- .. ...
+/// end
+///
+/// order intro intro
+/// ```
+///
+/// Each row has one cell per channel (pulse 1, pulse 2, wave, noise). A cell
+/// is a note (`C-4`, `C#4`, `---` for none, `OFF` to stop), an instrument in
+/// hex (`..` for `00`), and an effect (`...` for none, `1xx` slide up, `2xx`
+/// slide down, `Bxx` jump to order, `Fxx` set speed).
+///
+/// Instruments are numbered from `0` in the order of declaration. Volume is
+/// `15` unless given.
+pub struct Music {}
+
+impl Music {
+    pub fn convert(path: &str) -> Result<String, BuildStepError> {
+        let source = fs::read_to_string(path)?;
+        Self::parse(path, &source)
+    }
+
+    /// Converts the source of the song file at `path`.
+    fn parse(path: &str, source: &str) -> Result<String, BuildStepError> {
+        let name = Path::new(path)
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or_default();
+        let name = identifier(name).map_err(|err| parse_error(path, 0, err))?;
+
+        let mut song = Song::new(name);
+        let mut lines = source.lines().enumerate();
+        while let Some((number, line)) = lines.next() {
+            let number = number + 1;
+            let line = strip_comment(line);
+            if line.is_empty() {
+                continue;
+            }
+
+            let (command, args) = line.split_once(' ').unwrap_or((line, ""));
+            let result = match command {
+                "speed" => parse_number(args.trim(), 1, 255).map(|speed| song.speed = speed),
+                "pulse" => parse_instrument(args, &mut song.pulse, pulse_instrument),
+                "wave" => parse_instrument(args, &mut song.wave, wave_instrument),
+                "noise" => parse_instrument(args, &mut song.noise, noise_instrument),
+                "pattern" => {
+                    let mut rows = Vec::new();
+                    let mut end = false;
+                    for (number, line) in lines.by_ref() {
+                        let line = strip_comment(line);
+                        if line.is_empty() {
+                            continue;
+                        }
+                        if line == "end" {
+                            end = true;
+                            break;
+                        }
+                        let row =
+                            parse_row(line).map_err(|err| parse_error(path, number + 1, err))?;
+                        rows.push((number + 1, row));
+                    }
+                    if !end {
+                        Err("pattern is not closed with `end`".to_string())
+                    } else if rows.is_empty() {
+                        Err("pattern has no rows".to_string())
+                    } else {
+                        identifier(args.trim()).and_then(|pattern| {
+                            if song.patterns.iter().any(|(name, _)| *name == pattern) {
+                                return Err(format!("pattern `{}` is already declared", pattern));
+                            }
+                            song.patterns.push((pattern, rows));
+                            Ok(())
+                        })
+                    }
+                }
+                "order" => args
+                    .split_whitespace()
+                    .map(|pattern| identifier(pattern).map(|pattern| (number, pattern)))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map(|order| song.order.extend(order)),
+                _ => Err(format!("unknown command `{}`", command)),
+            };
+            result.map_err(|err| parse_error(path, number, err))?;
+        }
+
+        song.generate()
+            .map_err(|(line, err)| parse_error(path, line, err))
+    }
+}
+
+impl BuildStep for Music {
+    fn run(dir: &crate::WorkingDirectory, bar: &ProgressBar) -> Result<(), BuildStepError> {
+        bar.set_message("Music Converting...");
+
+        let mut code = String::from(
+            "// Generated by `cargo build-rom` from `./music`. Do not edit.\n\
+             // Requires the `prototype` feature of rust-gb.\n",
+        );
+        for song_path in dir.get_music_paths() {
+            match Self::convert(&song_path) {
+                Ok(song) => code.push_str(&song),
+                Err(err) => {
+                    bar.println(format!("{}", err));
+                    bar.finish_with_message(format!("{}", "Music Converting Failed".red()));
+                    return Err(err);
+                }
+            }
+        }
+
+        fs::write(format!("{}/music.rs", dir.out), code)?;
+        bar.finish_with_message(format!("{}", "Music Converting Succeeded".green()));
+        Ok(())
+    }
+}
+
+struct Song {
+    name: String,
+    speed: u8,
+    pulse: Vec<String>,
+    wave: Vec<String>,
+    noise: Vec<String>,
+    patterns: Vec<(String, Vec<Row>)>,
+    /// Line number and name of each pattern in the order.
+    order: Vec<(usize, String)>,
+}
+
+impl Song {
+    fn new(name: String) -> Self {
+        Self {
+            name,
+            speed: 6,
+            pulse: Vec::new(),
+            wave: Vec::new(),
+            noise: Vec::new(),
+            patterns: Vec::new(),
+            order: Vec::new(),
+        }
+    }
+
+    /// Generates the statics of the song, or returns an error with its line
+    /// number. (`0` if it is not about a line)
+    fn generate(&self) -> Result<String, (usize, String)> {
+        if self.order.is_empty() {
+            return Err((0, "song has no order".to_string()));
+        }
+
+        let mut code = String::new();
+        for (pattern, rows) in &self.patterns {
+            code.push_str(&format!(
+                "\nstatic {}_{}: [gb::music::Row; {}] = [\n",
+                self.name,
+                pattern,
+                rows.len()
+            ));
+            for (line, row) in rows {
+                self.check_row(row).map_err(|err| (*line, err))?;
+                let cells: Vec<_> = row.iter().map(|cell| cell.code.as_str()).collect();
+                code.push_str(&format!("    [{}],\n", cells.join(", ")));
+            }
+            code.push_str("];\n");
+        }
+
+        let mut order = Vec::new();
+        for (line, pattern) in &self.order {
+            if !self.patterns.iter().any(|(name, _)| name == pattern) {
+                return Err((
+                    *line,
+                    format!("order refers to unknown pattern `{}`", pattern),
+                ));
+            }
+            order.push(format!("&{}_{}", self.name, pattern));
+        }
+
+        code.push_str(&format!(
+            "\npub static {}: gb::music::Song = gb::music::Song {{\n    \
+             speed: {},\n    \
+             order: &[{}],\n    \
+             pulse_instruments: &[{}],\n    \
+             wave_instruments: &[{}],\n    \
+             noise_instruments: &[{}],\n\
+             }};\n",
+            self.name,
+            self.speed,
+            order.join(", "),
+            self.pulse.join(", "),
+            self.wave.join(", "),
+            self.noise.join(", "),
+        ));
+        Ok(code)
+    }
+
+    /// Checks the instruments against the instruments of each channel, and
+    /// the jumps against the order, which the player doesn't check.
+    fn check_row(&self, row: &[Cell; 4]) -> Result<(), String> {
+        let channels = [
+            ("pulse", &self.pulse),
+            ("pulse", &self.pulse),
+            ("wave", &self.wave),
+            ("noise", &self.noise),
+        ];
+        for (cell, (kind, instruments)) in row.iter().zip(channels) {
+            if let Some(instrument) = cell.instrument {
+                if instrument as usize >= instruments.len() {
+                    return Err(format!(
+                        "{} instrument {:02X} is not declared",
+                        kind, instrument
+                    ));
+                }
+            }
+            if let Some(jump) = cell.jump {
+                if jump as usize >= self.order.len() {
+                    return Err(format!(
+                        "jump to order {:02X} is out of the order of {} patterns",
+                        jump,
+                        self.order.len()
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Line number and cells of a pattern row.
+type Row = (usize, [Cell; 4]);
+
+/// Parsed cell of a pattern row.
+struct Cell {
+    /// Expression of the `gb::music::Cell`.
+    code: String,
+    /// Instrument of the note, if the cell has a note.
+    instrument: Option<u8>,
+    /// Order index of the `Bxx` effect.
+    jump: Option<u8>,
+}
+
+/// `key=value` parameters of an instrument.
+type Params<'a> = [(&'a str, &'a str)];
+
+fn parse_error(path: &str, line: usize, message: String) -> BuildStepError {
+    BuildStepError::MusicParseFailed(path.to_string(), line, message)
+}
+
+fn strip_comment(line: &str) -> &str {
+    let line = line.trim();
+    if line.starts_with('#') {
+        ""
+    } else {
+        line
+    }
+}
+
+fn identifier(name: &str) -> Result<String, String> {
+    let valid = !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if valid {
+        Ok(name.to_ascii_uppercase())
+    } else {
+        Err(format!("`{}` is not a valid name", name))
+    }
+}
+
+fn parse_number<T: TryFrom<u32>>(value: &str, min: u32, max: u32) -> Result<T, String> {
+    match value.parse::<u32>() {
+        Ok(number) if (min..=max).contains(&number) => {
+            T::try_from(number).map_err(|_| format!("`{}` is out of range", value))
+        }
+        _ => Err(format!("`{}` must be a number in {}..={}", value, min, max)),
+    }
+}
+
+/// Parses `<index> key=value...`, checking that instruments are declared in order.
+fn parse_instrument(
+    args: &str,
+    instruments: &mut Vec<String>,
+    parse: fn(&Params) -> Result<String, String>,
+) -> Result<(), String> {
+    let mut args = args.split_whitespace();
+    let index: usize = parse_number(args.next().unwrap_or_default(), 0, 255)?;
+    if index != instruments.len() {
+        return Err(format!(
+            "instrument {} must be declared after instrument {}",
+            index,
+            instruments.len()
+        ));
+    }
+
+    let params = args
+        .map(|arg| {
+            arg.split_once('=')
+                .ok_or_else(|| format!("`{}` must be in `key=value` form", arg))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    instruments.push(parse(&params)?);
+    Ok(())
+}
+
+fn envelope(params: &Params) -> Result<String, String> {
+    let mut volume = 15;
+    let mut envelope = String::new();
+    for (key, value) in params {
+        match *key {
+            "volume" => volume = parse_number(value, 0, 15)?,
+            "envelope" => {
+                let (direction, pace) = match value.split_once(':') {
+                    Some(("up", pace)) => ("Increase", pace),
+                    Some(("down", pace)) => ("Decrease", pace),
+                    _ if *value == "none" => continue,
+                    _ => return Err(format!("envelope `{}` must be `up:N` or `down:N`", value)),
+                };
+                let pace: u8 = parse_number(pace, 1, 7)?;
+                envelope = format!(
+                    ".with_direction(gb::sound::EnvelopeDirection::{}).with_pace({})",
+                    direction, pace
+                );
+            }
+            _ => {}
+        }
+    }
+    Ok(format!(
+        "gb::sound::Envelope::new().with_volume({}){}",
+        volume, envelope
+    ))
+}
+
+fn pulse_instrument(params: &Params) -> Result<String, String> {
+    let mut tone = format!(
+        "gb::sound::PulseTone::new().with_envelope({})",
+        envelope(params)?
+    );
+    for (key, value) in params {
+        match *key {
+            "duty" => {
+                let duty = match *value {
+                    "12" => "Eighth",
+                    "25" => "Quarter",
+                    "50" => "Half",
+                    "75" => "ThreeQuarters",
+                    _ => return Err(format!("duty `{}` must be 12, 25, 50 or 75", value)),
+                };
+                tone.push_str(&format!(".with_duty(gb::sound::DutyCycle::{})", duty));
+            }
+            "length" => {
+                let length: u8 = parse_number(value, 1, 64)?;
+                tone.push_str(&format!(".with_length({})", length));
+            }
+            "volume" | "envelope" => {}
+            _ => return Err(format!("unknown pulse parameter `{}`", key)),
+        }
+    }
+    Ok(tone)
+}
+
+fn wave_instrument(params: &Params) -> Result<String, String> {
+    let mut tone = "gb::sound::WaveTone::new()".to_string();
+    let mut waveform = "gb::sound::Waveform::TRIANGLE".to_string();
+    for (key, value) in params {
+        match *key {
+            "volume" => {
+                let volume = match *value {
+                    "0" => "Mute",
+                    "25" => "Quarter",
+                    "50" => "Half",
+                    "100" => "Full",
+                    _ => return Err(format!("wave volume `{}` must be 0, 25, 50 or 100", value)),
+                };
+                tone.push_str(&format!(".with_volume(gb::sound::WaveVolume::{})", volume));
+            }
+            "length" => {
+                let length: u16 = parse_number(value, 1, 256)?;
+                tone.push_str(&format!(".with_length({})", length));
+            }
+            "wave" => {
+                waveform = match *value {
+                    "square" => "gb::sound::Waveform::SQUARE".to_string(),
+                    "triangle" => "gb::sound::Waveform::TRIANGLE".to_string(),
+                    "sawtooth" => "gb::sound::Waveform::SAWTOOTH".to_string(),
+                    samples => {
+                        if samples.len() != 32 || !samples.chars().all(|c| c.is_ascii_hexdigit()) {
+                            return Err(format!(
+                                "wave `{}` must be square, triangle, sawtooth or 32 hex samples",
+                                value
+                            ));
+                        }
+                        let bytes: Vec<_> = (0..16)
+                            .map(|i| format!("0x{}", &samples[i * 2..i * 2 + 2]))
+                            .collect();
+                        format!("gb::sound::Waveform([{}])", bytes.join(", "))
+                    }
+                };
+            }
+            _ => return Err(format!("unknown wave parameter `{}`", key)),
+        }
+    }
+    Ok(format!(
+        "gb::music::WaveInstrument {{ tone: {}, waveform: &{} }}",
+        tone, waveform
+    ))
+}
+
+fn noise_instrument(params: &Params) -> Result<String, String> {
+    let mut tone = format!(
+        "gb::sound::NoiseTone::new().with_envelope({})",
+        envelope(params)?
+    );
+    let mut noise = "gb::sound::NoiseControl::new()".to_string();
+    for (key, value) in params {
+        match *key {
+            "shift" => {
+                let shift: u8 = parse_number(value, 0, 15)?;
+                noise.push_str(&format!(".with_clock_shift({})", shift));
+            }
+            "width" => {
+                let width = match *value {
+                    "7" => "Bits7",
+                    "15" => "Bits15",
+                    _ => return Err(format!("noise width `{}` must be 7 or 15", value)),
+                };
+                noise.push_str(&format!(".with_width(gb::sound::LfsrWidth::{})", width));
+            }
+            "divider" => {
+                let divider: u8 = parse_number(value, 0, 7)?;
+                noise.push_str(&format!(".with_divider({})", divider));
+            }
+            "length" => {
+                let length: u8 = parse_number(value, 1, 64)?;
+                tone.push_str(&format!(".with_length({})", length));
+            }
+            "volume" | "envelope" => {}
+            _ => return Err(format!("unknown noise parameter `{}`", key)),
+        }
+    }
+    Ok(format!("{}.with_noise({})", tone, noise))
+}
+
+fn parse_row(line: &str) -> Result<[Cell; 4], String> {
+    let cells: Vec<_> = line.split('|').map(parse_cell).collect::<Result<_, _>>()?;
+    cells
+        .try_into()
+        .map_err(|_| format!("row `{}` must have 4 cells", line))
+}
+
+fn parse_cell(cell: &str) -> Result<Cell, String> {
+    let mut tokens = cell.split_whitespace();
+    let note = tokens.next().unwrap_or("---");
+    let instrument = tokens.next().unwrap_or("..");
+    let effect = tokens.next().unwrap_or("...");
+    if tokens.next().is_some() {
+        return Err(format!("cell `{}` has too many columns", cell.trim()));
+    }
+
+    let instrument = match instrument {
+        ".." => 0,
+        _ => u8::from_str_radix(instrument, 16)
+            .map_err(|_| format!("instrument `{}` must be 2 hex digits", instrument))?,
+    };
+
+    let (mut code, instrument) = match note {
+        "---" => ("gb::music::Cell::EMPTY".to_string(), None),
+        "OFF" => ("gb::music::Cell::OFF".to_string(), None),
+        _ => (
+            format!(
+                "gb::music::Cell::note(gb::sound::Note::from_index({}), {})",
+                note_index(note)?,
+                instrument
+            ),
+            Some(instrument),
+        ),
+    };
+    let mut jump = None;
+
+    if effect != "..." {
+        let (kind, param) = effect.split_at_checked(1).unwrap_or_default();
+        let param = u8::from_str_radix(param, 16)
+            .ok()
+            .filter(|_| param.len() == 2)
+            .ok_or_else(|| format!("effect `{}` must be a letter and 2 hex digits", effect))?;
+        let effect = match kind {
+            "1" => "SlideUp",
+            "2" => "SlideDown",
+            "B" => {
+                jump = Some(param);
+                "Jump"
+            }
+            "F" => "Speed",
+            _ => return Err(format!("unknown effect `{}`", effect)),
+        };
+        code.push_str(&format!(
+            ".with_effect(gb::music::Effect::{}({}))",
+            effect, param
+        ));
+    }
+
+    Ok(Cell {
+        code,
+        instrument,
+        jump,
+    })
+}
+
+/// Converts a note like `C-4` or `F#5` into the number of semitones from C2.
+fn note_index(note: &str) -> Result<u8, String> {
+    let error = || {
+        format!(
+            "note `{}` must be like `C-4` or `F#5`, from C-2 to B-8",
+            note
+        )
+    };
+    let mut chars = note.chars();
+    let pitch = match chars.next() {
+        Some('C') => 0,
+        Some('D') => 2,
+        Some('E') => 4,
+        Some('F') => 5,
+        Some('G') => 7,
+        Some('A') => 9,
+        Some('B') => 11,
+        _ => return Err(error()),
+    };
+    let sharp = match chars.next() {
+        Some('-') => 0,
+        Some('#') if pitch != 4 && pitch != 11 => 1,
+        _ => return Err(error()),
+    };
+    let octave = match chars.next().and_then(|c| c.to_digit(10)) {
+        Some(octave @ 2..=8) if chars.next().is_none() => octave as u8,
+        _ => return Err(error()),
+    };
+    Ok((octave - 2) * 12 + pitch + sharp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "\
+speed 6
+pulse 0 duty=50 volume=12 envelope=down:2
+wave 0 wave=triangle volume=100
+noise 0 volume=15 shift=4 width=7 divider=2
+";
+
+    fn parse(body: &str) -> Result<String, (usize, String)> {
+        let source = format!("{}{}", HEADER, body);
+        Music::parse("music/theme.song", &source).map_err(|err| match err {
+            BuildStepError::MusicParseFailed(_, line, message) => (line, message),
+            err => panic!("unexpected error: {}", err),
+        })
+    }
+
+    #[test]
+    fn valid_song() {
+        let code = parse(
+            "pattern intro
+C-4 00 ... | --- .. ... | C-3 00 ... | --- .. ...
+OFF .. ... | --- .. ... | OFF .. B00 | C-4 00 ...
+end
+order intro intro
+",
+        )
+        .unwrap();
+        assert!(code.contains("static THEME_INTRO: [gb::music::Row; 2]"));
+        assert!(code.contains("pub static THEME: gb::music::Song"));
+        assert!(code.contains("order: &[&THEME_INTRO, &THEME_INTRO]"));
+        assert!(code.contains("gb::music::Effect::Jump(0)"));
+    }
+
+    #[test]
+    fn unclosed_pattern() {
+        let err = parse("pattern intro\nC-4 00 ... | --- .. ... | --- .. ... | --- .. ...\n");
+        assert_eq!(
+            err,
+            Err((5, "pattern is not closed with `end`".to_string()))
+        );
+    }
+
+    #[test]
+    fn empty_pattern() {
+        let err = parse("pattern intro\nend\norder intro\n");
+        assert_eq!(err, Err((5, "pattern has no rows".to_string())));
+    }
+
+    #[test]
+    fn unknown_pattern_in_order() {
+        let err = parse(
+            "pattern intro
+C-4 00 ... | --- .. ... | --- .. ... | --- .. ...
+end
+order intro outro
+",
+        );
+        assert_eq!(
+            err,
+            Err((8, "order refers to unknown pattern `OUTRO`".to_string()))
+        );
+    }
+
+    #[test]
+    fn jump_out_of_order() {
+        let err = parse(
+            "pattern intro
+C-4 00 B01 | --- .. ... | --- .. ... | --- .. ...
+end
+order intro
+",
+        );
+        assert_eq!(
+            err,
+            Err((
+                6,
+                "jump to order 01 is out of the order of 1 patterns".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn missing_instrument() {
+        let err = parse(
+            "pattern intro
+--- .. ... | --- .. ... | C-4 01 ... | --- .. ...
+end
+order intro
+",
+        );
+        assert_eq!(
+            err,
+            Err((6, "wave instrument 01 is not declared".to_string()))
+        );
+    }
+
+    #[test]
+    fn duplicate_pattern() {
+        let err = parse(
+            "pattern intro
+C-4 00 ... | --- .. ... | --- .. ... | --- .. ...
+end
+pattern intro
+C-3 00 ... | --- .. ... | --- .. ... | --- .. ...
+end
+order intro
+",
+        );
+        assert_eq!(
+            err,
+            Err((8, "pattern `INTRO` is already declared".to_string()))
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use std::process::Command;
+use std::{fs, process::Command};
 
 use colored::Colorize;
 use indicatif::ProgressBar;
@@ -69,6 +69,21 @@ impl BuildStep for Sdcc {
             return Err(err);
         }
 
+        // Banked files are compiled into their own directory, so that stale ones are not linked.
+        let banked_asm_dir = format!("{}/asm/banked", dir.out);
+        if fs::exists(&banked_asm_dir)? {
+            fs::remove_dir_all(&banked_asm_dir)?;
+        }
+        fs::create_dir_all(&banked_asm_dir)?;
+        for c_file in dir.get_banked_c_paths() {
+            let (_, out_name) = c_file.rsplit_once('/').unwrap();
+            let out_name = format!("{}/{}.asm", banked_asm_dir, out_name);
+            if let Err(err) = Self::compile(&c_file, out_name.as_str(), &bar) {
+                bar.finish_with_message(format!("{}", "C -> ASM Compiling Failed".red()));
+                return Err(err);
+            }
+        }
+
         let c_path = dir.get_c_paths();
         for c_file in c_path {
             let (_, out_name) = c_file.rsplit_once('/').unwrap();
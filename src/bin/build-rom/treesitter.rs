@@ -9,16 +9,13 @@ use indicatif::ProgressBar;
 use streaming_iterator::StreamingIterator;
 use tree_sitter::{Query, QueryCursor};
 
-use crate::{BuildStep, BuildStepError};
+use crate::{banking, BuildStep, BuildStepError};
 
 pub struct Treesitter {}
 
-impl BuildStep for Treesitter {
-    fn run(dir: &crate::WorkingDirectory, bar: &ProgressBar) -> Result<(), BuildStepError> {
-        bar.set_message("Treesitter Running...");
-        let c_path = format!("{}/out.c", dir.out);
-
-        let mut code = fs::read_to_string(&c_path)?;
+impl Treesitter {
+    fn process(c_path: &str, bar: &ProgressBar) -> Result<(), BuildStepError> {
+        let mut code = fs::read_to_string(c_path)?;
         let code_bytes = code.clone();
         let code_bytes = code_bytes.as_bytes();
         parse_declarator_attributes(&mut code, code_bytes);
@@ -27,7 +24,7 @@ impl BuildStep for Treesitter {
         let code_bytes = code_bytes.as_bytes();
         parse_call_expression_attributes(&mut code, code_bytes);
 
-        let mut file = File::create(&c_path)?;
+        let mut file = File::create(c_path)?;
         file.write_all(&code.as_bytes())?;
 
         //Remove All Global Variable Declarations (Because it is mostly duplicated with Global Variable Definitions)
@@ -36,16 +33,14 @@ impl BuildStep for Treesitter {
             .args([
                 "/.*Global Variable Declarations.*/,/.*Function Declarations.*/{/^\\//!d;}",
                 "-i",
-                c_path.as_str(),
+                c_path,
             ])
             .output()
         {
             if output.status.success() {
-                bar.finish_with_message(format!("{}", "Treesitter Run Succeeded".green()));
                 return Ok(());
             } else {
                 bar.println(String::from_utf8_lossy(&output.stderr));
-                bar.finish_with_message(format!("{}", "Treesitter Run Failed".red()));
                 return Err(BuildStepError::ChildProcessFailed(
                     "sed".to_string(),
                     output.status,
@@ -53,10 +48,50 @@ impl BuildStep for Treesitter {
             }
         } else {
             bar.println("sed execution failed");
-            bar.finish_with_message(format!("{}", "Treesitter Run Failed".red()));
             Err(BuildStepError::ChildExecutionFailed("sed".to_string()))
         }
     }
+
+    // Split banked functions and statics into `out/banked`, to be autobanked.
+    fn split_banked(dir: &crate::WorkingDirectory, c_path: &str) -> Result<(), BuildStepError> {
+        let mut code = fs::read_to_string(c_path)?;
+        let banked = banking::split_banked(&mut code);
+        fs::write(c_path, &code)?;
+
+        let banked_dir = format!("{}/banked", dir.out);
+        if fs::exists(&banked_dir)? {
+            fs::remove_dir_all(&banked_dir)?;
+        }
+        fs::create_dir_all(&banked_dir)?;
+        for (i, code) in banked.iter().enumerate() {
+            fs::write(format!("{}/banked_{}.c", banked_dir, i), code)?;
+        }
+        Ok(())
+    }
+}
+
+impl BuildStep for Treesitter {
+    fn run(dir: &crate::WorkingDirectory, bar: &ProgressBar) -> Result<(), BuildStepError> {
+        bar.set_message("Treesitter Running...");
+        let c_path = format!("{}/out.c", dir.out);
+
+        let mut c_paths = vec![c_path.clone()];
+        let result = Self::split_banked(dir, &c_path).and_then(|_| {
+            c_paths.append(&mut dir.get_banked_c_paths());
+            c_paths.iter().try_for_each(|path| Self::process(path, bar))
+        });
+
+        match result {
+            Ok(()) => {
+                bar.finish_with_message(format!("{}", "Treesitter Run Succeeded".green()));
+                Ok(())
+            }
+            Err(err) => {
+                bar.finish_with_message(format!("{}", "Treesitter Run Failed".red()));
+                Err(err)
+            }
+        }
+    }
 }
 
 fn parse_declarator_attributes(code: &mut String, code_bytes: &[u8]) {
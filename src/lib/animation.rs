@@ -0,0 +1,230 @@
+//! Frame sequencing for sprite animations.
+//!
+//! An [`Animation`] steps through a constant list of frames, each held for a
+//! number of VBlanks. The value of a frame can be anything `Copy`, usually a
+//! tile index for a [`Sprite`] or a [`Metasprite`].
+//!
+//! [`Animation::update`] counts VBlanks with [`crate::lcd::frame_count`], so the
+//! animation keeps its speed even if the game loop misses a frame.
+//!
+//! [`Sprite`]: crate::sprite::Sprite
+//! [`Metasprite`]: crate::metasprite::Metasprite
+//!
+//! # Examples
+//! ```
+//! const WALK: [Frame<u8>; 3] = [Frame::new(0, 8), Frame::new(1, 8), Frame::new(2, 8)];
+//!
+//! let mut walk = Animation::new(&WALK, AnimationMode::PingPong);
+//! let mut player = Sprite::new().unwrap();
+//!
+//! loop {
+//!     if walk.update() {
+//!         player.set_tile(walk.frame());
+//!     }
+//!     // wait for next frame
+//! }
+//! ```
+
+use crate::lcd::FrameTimer;
+
+/// One frame of an animation.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Frame<T> {
+    /// Value of the frame, such as a tile index.
+    pub value: T,
+    /// Number of VBlanks until the next frame. `0` is treated as `1`.
+    pub frames: u8,
+}
+
+impl<T> Frame<T> {
+    /// Creates a frame held for given VBlanks.
+    pub const fn new(value: T, frames: u8) -> Self {
+        Frame { value, frames }
+    }
+}
+
+/// What an animation does after the last frame.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AnimationMode {
+    /// Restart from the first frame.
+    Loop,
+    /// Play backward to the first frame, then forward again.
+    PingPong,
+    /// Stop at the last frame.
+    Once,
+}
+
+/// Frame sequencer of an animation.
+pub struct Animation<T: Copy + 'static> {
+    frames: &'static [Frame<T>],
+    mode: AnimationMode,
+    index: u8,
+    backward: bool,
+    elapsed: u8,
+    timer: FrameTimer,
+    finished: bool,
+    paused: bool,
+    on_end: Option<fn()>,
+}
+
+impl<T: Copy + 'static> Animation<T> {
+    /// Creates an animation at its first frame.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no frame, or there are more than 255 frames.
+    pub const fn new(frames: &'static [Frame<T>], mode: AnimationMode) -> Self {
+        if frames.is_empty() || frames.len() > u8::MAX as usize {
+            panic!("Animation frame count outbounded");
+        }
+        Animation {
+            frames,
+            mode,
+            index: 0,
+            backward: false,
+            elapsed: 0,
+            timer: FrameTimer::new(),
+            finished: false,
+            paused: false,
+            on_end: None,
+        }
+    }
+
+    /// Set a function called at the end of the animation.
+    ///
+    /// It is called every time a [`AnimationMode::Loop`] animation wraps, a
+    /// [`AnimationMode::PingPong`] animation returns to the first frame, and
+    /// once when a [`AnimationMode::Once`] animation finishes.
+    pub const fn with_on_end(self, on_end: fn()) -> Self {
+        Animation {
+            on_end: Some(on_end),
+            ..self
+        }
+    }
+
+    /// Value of the current frame.
+    pub fn frame(&self) -> T {
+        self.frames[self.index as usize].value
+    }
+
+    /// Index of the current frame.
+    pub fn index(&self) -> u8 {
+        self.index
+    }
+
+    /// Whether a [`AnimationMode::Once`] animation has finished.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Restart the animation from the first frame.
+    pub fn restart(&mut self) {
+        self.index = 0;
+        self.backward = false;
+        self.elapsed = 0;
+        self.timer = FrameTimer::new();
+        self.finished = false;
+    }
+
+    /// Change the frames of the animation, and restart it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no frame, or there are more than 255 frames.
+    pub fn set_frames(&mut self, frames: &'static [Frame<T>], mode: AnimationMode) {
+        if frames.is_empty() || frames.len() > u8::MAX as usize {
+            panic!("Animation frame count outbounded");
+        }
+        self.frames = frames;
+        self.mode = mode;
+        self.restart();
+    }
+
+    /// Stop advancing the animation.
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    /// Continue the animation paused by [`Animation::pause`].
+    pub fn resume(&mut self) {
+        self.paused = false;
+        self.timer = FrameTimer::new();
+    }
+
+    /// Advance the animation by VBlanks elapsed since the last call.
+    ///
+    /// The first call after creating or restarting only starts counting.
+    /// Returns whether the current frame has changed.
+    pub fn update(&mut self) -> bool {
+        let elapsed = self.timer.elapsed();
+        self.advance(elapsed)
+    }
+
+    /// Advance the animation by given VBlanks.
+    ///
+    /// Returns whether the current frame has changed, even if the animation
+    /// went through a whole cycle back to the same frame.
+    pub fn advance(&mut self, frames: u16) -> bool {
+        if self.paused {
+            return false;
+        }
+        let mut changed = false;
+        for _ in 0..frames {
+            if self.finished {
+                break;
+            }
+            self.elapsed += 1;
+            if self.elapsed >= self.frames[self.index as usize].frames {
+                self.elapsed = 0;
+                self.next_frame();
+                // The last frame of `Once` is kept when it finishes.
+                changed |= !self.finished;
+            }
+        }
+        changed
+    }
+
+    fn next_frame(&mut self) {
+        let last = self.frames.len() as u8 - 1;
+        match self.mode {
+            AnimationMode::Loop => {
+                if self.index == last {
+                    self.index = 0;
+                    self.end();
+                } else {
+                    self.index += 1;
+                }
+            }
+            AnimationMode::Once => {
+                if self.index == last {
+                    self.finished = true;
+                    self.end();
+                } else {
+                    self.index += 1;
+                }
+            }
+            AnimationMode::PingPong => {
+                if last == 0 {
+                    self.end();
+                } else if self.backward {
+                    self.index -= 1;
+                    if self.index == 0 {
+                        self.backward = false;
+                        self.end();
+                    }
+                } else {
+                    self.index += 1;
+                    if self.index == last {
+                        self.backward = true;
+                    }
+                }
+            }
+        }
+    }
+
+    fn end(&self) {
+        if let Some(on_end) = self.on_end {
+            on_end();
+        }
+    }
+}
@@ -0,0 +1,116 @@
+//! Background layer.
+//!
+//! The background is a 32x32 tile map (256x256 pixels), and the 160x144
+//! screen shows a part of it selected by the SCX and SCY registers. It wraps
+//! around when scrolled past the edge.
+//!
+//! Note that [`crate::io::GbStream`] also draws on the background, using the
+//! tile map at `$9800` and tiles loaded by GBDK.
+//!
+//! # Examples
+//! ```
+//! lcd::with_lcd_off(|| {
+//!     tiles::load_tiles(0, &TILES);
+//!     Background::set_tile_data(TileDataArea::Area8000);
+//!     Background::set_tiles(0, 0, 20, 18, &MAP);
+//! });
+//! Background::show();
+//!
+//! loop {
+//!     Background::scroll_by(1, 0);
+//!     // wait for next frame
+//! }
+//! ```
+//!
+//! Documents are provided by [GB Pan Docs](https://gbdev.io/pandocs/Scrolling.html)
+
+use crate::{
+    lcd, mmio,
+    register::{self, TileDataArea, TileMapArea},
+    tiles::TileMap,
+};
+
+/// Background layer of the screen.
+pub struct Background {
+    private: (),
+}
+
+impl Background {
+    /// Tile map used by the background, selected by LCDC.
+    pub fn map() -> TileMap {
+        TileMap::new(register::LCDC.read().bg_map())
+    }
+
+    /// Select the tile map used by the background.
+    pub fn set_map_area(area: TileMapArea) {
+        lcd::update_lcdc(|lcdc| {
+            lcdc.set_bg_map(area);
+        });
+    }
+
+    /// Tile data area used by the background (and the window).
+    pub fn tile_data() -> TileDataArea {
+        register::LCDC.read().tile_data()
+    }
+
+    /// Select the tile data area used by the background (and the window).
+    pub fn set_tile_data(area: TileDataArea) {
+        lcd::update_lcdc(|lcdc| {
+            lcdc.set_tile_data(area);
+        });
+    }
+
+    /// Show the background.
+    ///
+    /// In the GameBoy Color mode, this bit means BG and window priority
+    /// instead. (See [Pan Docs](https://gbdev.io/pandocs/LCDC.html#lcdc0--bg-and-window-enablepriority))
+    pub fn show() {
+        lcd::update_lcdc(|lcdc| {
+            lcdc.set_bg_enabled(true);
+        });
+    }
+
+    /// Hide the background (and the window), filling it with color 0.
+    pub fn hide() {
+        lcd::update_lcdc(|lcdc| {
+            lcdc.set_bg_enabled(false);
+        });
+    }
+
+    /// Set a tile of the background tile map.
+    pub fn set_tile(x: u8, y: u8, tile: u8) {
+        Self::map().set_tile(x, y, tile);
+    }
+
+    /// Set a rectangle of the background tile map, given in row-major order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tiles` is shorter than `width * height`.
+    pub fn set_tiles(x: u8, y: u8, width: u8, height: u8, tiles: &[u8]) {
+        Self::map().set_tiles(x, y, width, height, tiles);
+    }
+
+    /// Fill the whole background tile map with one tile.
+    pub fn clear(tile: u8) {
+        Self::map().clear(tile);
+    }
+
+    /// Scroll the background so that the pixel `(x, y)` is at the top left of
+    /// the screen.
+    pub fn scroll(x: u8, y: u8) {
+        mmio::SCX.write(x);
+        mmio::SCY.write(y);
+    }
+
+    /// Scroll the background relatively, wrapping around.
+    pub fn scroll_by(dx: i8, dy: i8) {
+        mmio::SCX.write(mmio::SCX.read().wrapping_add_signed(dx));
+        mmio::SCY.write(mmio::SCY.read().wrapping_add_signed(dy));
+    }
+
+    /// Current scroll position `(x, y)`.
+    pub fn position() -> (u8, u8) {
+        (mmio::SCX.read(), mmio::SCY.read())
+    }
+}
@@ -0,0 +1,52 @@
+//! CPU speed of the GameBoy Color.
+//!
+//! The GameBoy Color CPU can run at double speed (8 MHz). In double speed
+//! mode, the CPU, DIV, TIMA, serial and OAM DMA are twice as fast, while the
+//! PPU, audio and HDMA keep their speed.
+//!
+//! Documents are provided by [GB Pan Docs](https://gbdev.io/pandocs/CGB_Registers.html#ff4d--key1-cgb-mode-only-prepare-speed-switch)
+
+use crate::{
+    register::{self, InterruptFlags, Joyp, Key1},
+    system,
+};
+
+extern "C" {
+    fn stop();
+}
+
+/// Whether the CPU is in double speed mode.
+///
+/// Always `false` out of CGB mode, where KEY1 doesn't exist or is locked.
+/// (See [`system::is_cgb_mode`])
+pub fn is_double_speed() -> bool {
+    system::is_cgb_mode() && register::KEY1.read().double_speed()
+}
+
+/// Switch the CPU to double speed mode or back to single speed mode.
+///
+/// Does nothing if the CPU is already in the mode. During the switch, every
+/// interrupt is masked in IE, and pending interrupts are cleared. The CPU is
+/// stopped for about 2050 M-cycles and the LCD shows nothing meanwhile, so it
+/// is better to switch during VBlank or with the LCD off.
+///
+/// Out of CGB mode, the speed never changes and it does nothing.
+pub fn set_double_speed(enabled: bool) {
+    if !system::is_cgb_mode() || is_double_speed() == enabled {
+        return;
+    }
+
+    let ie = register::IE.read();
+    register::IE.write(InterruptFlags::new());
+    register::IF.write(InterruptFlags::new());
+
+    // SAFETY: Selecting no joypad line prevents STOP from being interrupted.
+    unsafe { register::JOYP.write(Joyp::NONE) };
+    // SAFETY: Arming the switch only matters for the following STOP.
+    unsafe { register::KEY1.write(Key1::new().with_armed(true)) };
+    // SAFETY: The speed switch is armed and every interrupt is masked, so
+    // STOP only switches the speed and returns.
+    unsafe { stop() };
+
+    register::IE.write(ie);
+}
@@ -0,0 +1,222 @@
+//! VRAM DMA of the GameBoy Color.
+//!
+//! The GameBoy Color can copy data from ROM or RAM into VRAM with DMA, much
+//! faster than the CPU. There are two modes.
+//!
+//! * **General-purpose DMA** copies everything at once, and the CPU is halted
+//!   until it finishes. (About 8 μs per 16 bytes in single speed mode)
+//! * **HBlank DMA** copies 16 bytes in each HBlank, while the CPU keeps
+//!   running.
+//!
+//! Source and destination addresses must be aligned to 16 bytes, and the
+//! length must be a multiple of 16 bytes, up to 2048 bytes. [`DmaBuffer`]
+//! checks the length at compile time, and aligns its contents in RAM. Every
+//! transfer checks all of them at runtime.
+//!
+//! # Examples
+//! ```
+//! let mut tiles = DmaBuffer::<256>::new();
+//! tiles.as_mut_slice().copy_from_slice(&TILE_DATA);
+//!
+//! // Copy into tile 0 during VBlank.
+//! lcd::wait_vblank();
+//! dma::general_purpose(tiles.as_slice(), 0x8000).unwrap();
+//!
+//! // Copy into tile 16 across HBlanks.
+//! let transfer = HblankDma::start(tiles.as_slice(), 0x8100).unwrap();
+//! transfer.wait();
+//! ```
+//!
+//! Documents are provided by [GB Pan Docs](https://gbdev.io/pandocs/CGB_Registers.html#lcd-vram-dma-transfers)
+
+use crate::{
+    mmio,
+    register::{self, Hdma5},
+};
+
+/// Size of a DMA block.
+pub const BLOCK_SIZE: usize = 16;
+
+/// Maximum length of a transfer.
+pub const MAX_LENGTH: usize = 2048;
+
+/// Error of a VRAM DMA transfer.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DmaError {
+    /// The source or destination address is not aligned to 16 bytes.
+    Unaligned,
+    /// The length is not a multiple of 16 bytes between 16 and 2048.
+    InvalidLength,
+    /// The source is not in ROM, SRAM or WRAM. (`$0000-$7FFF`,
+    /// `$A000-$DFFF`)
+    InvalidSource,
+    /// The destination is out of VRAM.
+    InvalidDestination,
+    /// An HBlank DMA is active.
+    Busy,
+}
+
+/// Buffer of `N` bytes for DMA, whose contents are aligned to 16 bytes.
+///
+/// SDCC doesn't support alignment attributes, so the buffer reserves 15 more
+/// bytes, and its contents start at the first 16-byte boundary in it. The
+/// contents are only known at runtime, so they are written with
+/// [`DmaBuffer::as_mut_slice`], and the buffer must be in RAM.
+///
+/// `N` is checked to be a multiple of 16 between 16 and 2048 at compile time.
+#[repr(C)]
+pub struct DmaBuffer<const N: usize> {
+    data: [u8; N],
+    padding: [u8; BLOCK_SIZE - 1],
+}
+
+impl<const N: usize> DmaBuffer<N> {
+    const VALID: () = assert!(
+        N >= BLOCK_SIZE && N <= MAX_LENGTH && N.is_multiple_of(BLOCK_SIZE),
+        "DMA buffer length must be a multiple of 16 between 16 and 2048"
+    );
+
+    /// Creates a buffer filled with zeros.
+    pub const fn new() -> Self {
+        let () = Self::VALID;
+        DmaBuffer {
+            data: [0; N],
+            padding: [0; BLOCK_SIZE - 1],
+        }
+    }
+
+    /// Start of the contents, at the first 16-byte boundary of the buffer.
+    fn start(&self) -> *const u8 {
+        let address = self as *const Self as usize;
+        let offset = address.wrapping_neg() % BLOCK_SIZE;
+        (address + offset) as *const u8
+    }
+
+    /// Contents of the buffer.
+    pub fn as_slice(&self) -> &[u8] {
+        let () = Self::VALID;
+        // SAFETY: The contents are `N` bytes at most 15 bytes from the start
+        // of the buffer, which has `N + 15` bytes.
+        unsafe { core::slice::from_raw_parts(self.start(), N) }
+    }
+
+    /// Contents of the buffer, to be written.
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        let () = Self::VALID;
+        // SAFETY: Same as `as_slice`, borrowed mutably.
+        unsafe { core::slice::from_raw_parts_mut(self.start() as *mut u8, N) }
+    }
+}
+
+impl<const N: usize> Default for DmaBuffer<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Checks a transfer, and writes the source and destination registers.
+fn prepare(source: &[u8], destination: usize) -> Result<u8, DmaError> {
+    let source_address = source.as_ptr() as usize;
+    let length = source.len();
+
+    if is_active() {
+        return Err(DmaError::Busy);
+    }
+    if !source_address.is_multiple_of(BLOCK_SIZE) || !destination.is_multiple_of(BLOCK_SIZE) {
+        return Err(DmaError::Unaligned);
+    }
+    if length == 0 || length > MAX_LENGTH || !length.is_multiple_of(BLOCK_SIZE) {
+        return Err(DmaError::InvalidLength);
+    }
+    let source_end = source_address + length;
+    let in_rom = source_end <= 0x8000;
+    let in_ram = source_address >= 0xA000 && source_end <= 0xE000;
+    if !in_rom && !in_ram {
+        return Err(DmaError::InvalidSource);
+    }
+    if destination < 0x8000 || destination + length > 0xA000 {
+        return Err(DmaError::InvalidDestination);
+    }
+
+    mmio::HDMA1.write((source_address >> 8) as u8);
+    mmio::HDMA2.write(source_address as u8);
+    mmio::HDMA3.write((destination >> 8) as u8);
+    mmio::HDMA4.write(destination as u8);
+    Ok((length / BLOCK_SIZE - 1) as u8)
+}
+
+/// Copies `source` into VRAM at `destination` at once.
+///
+/// The CPU is halted until the copy finishes. VRAM must be accessible during
+/// the whole copy, so call it during VBlank or with the LCD off. The
+/// selected VRAM bank is written.
+pub fn general_purpose(source: &[u8], destination: usize) -> Result<(), DmaError> {
+    let blocks = prepare(source, destination)?;
+    // SAFETY: The transfer is checked to read valid memory and write VRAM.
+    unsafe { register::HDMA5.write(Hdma5::new().with_blocks(blocks)) };
+    Ok(())
+}
+
+/// Whether an HBlank DMA is active.
+pub fn is_active() -> bool {
+    !register::HDMA5.read().hblank_or_inactive()
+}
+
+/// Active HBlank DMA transfer.
+///
+/// Dropping it doesn't stop the transfer, which is why the source must be
+/// `'static`.
+pub struct HblankDma {
+    private: (),
+}
+
+impl HblankDma {
+    /// Starts copying `source` into VRAM at `destination`, 16 bytes per
+    /// HBlank.
+    ///
+    /// If the LCD is off, the first 16 bytes are copied immediately and the
+    /// rest are copied after the LCD is turned on. Don't switch the VRAM bank
+    /// during the transfer.
+    pub fn start(source: &'static [u8], destination: usize) -> Result<Self, DmaError> {
+        let blocks = prepare(source, destination)?;
+        // SAFETY: The transfer is checked to read valid memory and write VRAM.
+        unsafe {
+            register::HDMA5.write(
+                Hdma5::new()
+                    .with_hblank_or_inactive(true)
+                    .with_blocks(blocks),
+            )
+        };
+        Ok(HblankDma { private: () })
+    }
+
+    /// Whether the transfer is still active.
+    pub fn is_active(&self) -> bool {
+        is_active()
+    }
+
+    /// Number of bytes not copied yet.
+    pub fn remaining(&self) -> usize {
+        let hdma5 = register::HDMA5.read();
+        if hdma5.hblank_or_inactive() {
+            0
+        } else {
+            (hdma5.blocks() as usize + 1) * BLOCK_SIZE
+        }
+    }
+
+    /// Stops the transfer, and returns the number of bytes not copied.
+    pub fn cancel(self) -> usize {
+        if !is_active() {
+            return 0;
+        }
+        // SAFETY: Writing bit 7 as 0 during HBlank DMA stops the transfer.
+        unsafe { register::HDMA5.write(Hdma5::new()) };
+        (register::HDMA5.read().blocks() as usize + 1) * BLOCK_SIZE
+    }
+
+    /// Blocks until the transfer finishes.
+    pub fn wait(self) {
+        while is_active() {}
+    }
+}
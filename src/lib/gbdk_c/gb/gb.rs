@@ -26,7 +26,33 @@ pub const TIM_IFLAG: u8 = 0x04;
 pub const SIO_IFLAG: u8 = 0x08;
 pub const JOY_IFLAG: u8 = 0x10;
 
+pub const DMG_TYPE: u8 = 0x01;
+pub const MGB_TYPE: u8 = 0xFF;
+pub const CGB_TYPE: u8 = 0x11;
+
+pub const GBA_NOT_DETECTED: u8 = 0x00;
+pub const GBA_DETECTED: u8 = 0x01;
+
+pub const MAX_HARDWARE_SPRITES: u8 = 40;
+
+#[allow(non_camel_case_types)]
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct OAM_item_t {
+    pub y: u8,
+    pub x: u8,
+    pub tile: u8,
+    pub prop: u8,
+}
+
 extern "C" {
+    pub static mut shadow_OAM: [OAM_item_t; MAX_HARDWARE_SPRITES as usize];
+    pub static mut _shadow_OAM_base: u8;
+    pub static sys_time: u16;
+    pub static _cpu: u8;
+    pub static _is_GBA: u8;
+    pub static mut _current_bank: u8;
+
     pub fn remove_VBL(h: int_handler);
     pub fn remove_LCD(h: int_handler);
     pub fn remove_TIM(h: int_handler);
@@ -72,6 +98,9 @@ extern "C" {
 
     pub fn void();
 
+    #[link_name = "refresh_OAM __preserves_regs(b, c, d, e, h, l)"]
+    pub fn refresh_OAM();
+
     #[link_name = "vsync __preserves_regs(b, c, d, e, h, l)"]
     pub fn vsync();
 
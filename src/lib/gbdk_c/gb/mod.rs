@@ -4,3 +4,8 @@ pub mod drawing;
 /// Binding of GBDK's `gb/gb.h`
 #[allow(clippy::module_inception)]
 pub mod gb;
+
+/// Binding of GBDK's `gb/sgb.h`
+///
+/// See [`crate::sgb`].
+pub mod sgb;
@@ -1,7 +1,71 @@
-use crate::gbdk_c;
+//! Interrupt handlers and interrupt masking.
+//!
+//! An [`Interrupt`] is a handler for one kind of interrupt, and it is called
+//! until the [`InterruptGuard`] returned by [`Interrupt::register`] is
+//! dropped. The handlers of a kind are called in order by a dispatcher, which
+//! takes a single handler slot of GBDK.
+//!
+//! Registering a handler also sets the bit of the interrupt in `IE`, and the
+//! bit is cleared again when the last guard is dropped, unless it was already
+//! set before. The other bits of `IE` are kept. (The VBlank interrupt is
+//! enabled by GBDK at startup, for [`crate::lcd::frame_count`] and `vsync`.)
+//!
+//! Handlers are called with interrupts disabled, so they should be short.
+//! [`critical_section`], [`enable`] and [`disable`] don't change `IME` in
+//! handlers, so they can be called from both handlers and the main thread.
+//!
+//! # Examples
+//! ```
+//! use gb::irq::{Interrupt, InterruptKind};
+//!
+//! static mut FRAMES: u8 = 0;
+//!
+//! extern "C" fn count_frame() {
+//!     unsafe { FRAMES = FRAMES.wrapping_add(1) };
+//! }
+//!
+//! let guard = Interrupt::new(InterruptKind::VBlank, count_frame).register();
+//! // ...
+//! drop(guard); // `count_frame` is not called anymore.
+//!
+//! let frames = gb::irq::critical_section(|| unsafe { FRAMES });
+//! ```
+//!
+//! Documents are provided by [GB Pan Docs](https://gbdev.io/pandocs/Interrupts.html)
 
+use core::ptr::addr_of_mut;
+
+use crate::{
+    gbdk_c,
+    register::{self, InterruptFlags},
+};
+
+/// Whether `IME` is set, as it can't be read. GBDK enables interrupts
+/// before `main`.
+static mut IME: bool = true;
+
+/// Depth of nested [`critical_section`] calls.
+static mut CRITICAL_DEPTH: u8 = 0;
+
+/// Whether an interrupt handler is running, with `IME` cleared until `reti`.
+static mut IN_HANDLER: bool = false;
+
+/// Maximum number of handlers of each kind.
+pub const MAX_HANDLERS: usize = 4;
+
+/// Registered handlers of each kind, called in order by the dispatchers.
+static mut HANDLERS: [[Option<extern "C" fn()>; MAX_HANDLERS]; 5] = [[None; MAX_HANDLERS]; 5];
+
+/// Number of registered handlers of each kind.
+static mut HANDLER_COUNT: [u8; 5] = [0; 5];
+
+/// `IE` bits set by this module, which are cleared when the last handler of
+/// the kind is removed.
+static mut OWNED_FLAGS: InterruptFlags = InterruptFlags::new();
+
+/// Kind of interrupts.
 #[repr(u8)]
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum InterruptKind {
     VBlank = 0x01,
     LCD = 0x02,
@@ -10,45 +74,256 @@ pub enum InterruptKind {
     Joypad = 0x10,
 }
 
+impl InterruptKind {
+    /// `IE` and `IF` bit of the interrupt.
+    pub const fn flags(self) -> InterruptFlags {
+        InterruptFlags::from_bits(self as u8)
+    }
+
+    const fn index(self) -> usize {
+        match self {
+            InterruptKind::VBlank => 0,
+            InterruptKind::LCD => 1,
+            InterruptKind::Timer => 2,
+            InterruptKind::Serial => 3,
+            InterruptKind::Joypad => 4,
+        }
+    }
+
+    const fn dispatcher(self) -> extern "C" fn() {
+        match self {
+            InterruptKind::VBlank => dispatch_vblank,
+            InterruptKind::LCD => dispatch_lcd,
+            InterruptKind::Timer => dispatch_timer,
+            InterruptKind::Serial => dispatch_serial,
+            InterruptKind::Joypad => dispatch_joypad,
+        }
+    }
+}
+
+/// Call the handlers of `kind`, marking that a handler is running.
+fn dispatch(kind: InterruptKind) {
+    // SAFETY: Interrupts are disabled in the dispatchers, and nested
+    // interrupts are not enabled by this module.
+    unsafe {
+        let in_handler = &mut *addr_of_mut!(IN_HANDLER);
+        *in_handler = true;
+        for slot in 0..MAX_HANDLERS {
+            // The slots are read one by one, as a handler may remove itself
+            // or register another one.
+            if let Some(handler) = (*addr_of_mut!(HANDLERS))[kind.index()][slot] {
+                handler();
+            }
+        }
+        *in_handler = false;
+    }
+}
+
+extern "C" fn dispatch_vblank() {
+    dispatch(InterruptKind::VBlank);
+}
+
+extern "C" fn dispatch_lcd() {
+    dispatch(InterruptKind::LCD);
+}
+
+extern "C" fn dispatch_timer() {
+    dispatch(InterruptKind::Timer);
+}
+
+extern "C" fn dispatch_serial() {
+    dispatch(InterruptKind::Serial);
+}
+
+extern "C" fn dispatch_joypad() {
+    dispatch(InterruptKind::Joypad);
+}
+
+/// Interrupt handler of a kind.
 #[derive(Clone, Copy)]
 pub struct Interrupt {
     pub kind: InterruptKind,
-    pub handler: unsafe extern "C" fn(),
+    pub handler: extern "C" fn(),
 }
 
 impl Interrupt {
-    unsafe fn register(&self) {
-        match self.kind {
-            InterruptKind::VBlank => unsafe { gbdk_c::gb::gb::add_VBL(self.handler) },
-            InterruptKind::LCD => unsafe { gbdk_c::gb::gb::add_LCD(self.handler) },
-            InterruptKind::Timer => unsafe { gbdk_c::gb::gb::add_TIM(self.handler) },
-            InterruptKind::Serial => unsafe { gbdk_c::gb::gb::add_SIO(self.handler) },
-            InterruptKind::Joypad => unsafe { gbdk_c::gb::gb::add_JOY(self.handler) },
+    /// Creates an interrupt handler.
+    pub const fn new(kind: InterruptKind, handler: extern "C" fn()) -> Self {
+        Interrupt { kind, handler }
+    }
+
+    /// Register the handler, and enable the interrupt in `IE`.
+    ///
+    /// The handler is removed when the returned guard is dropped. Use
+    /// [`InterruptGuard::forget`] to keep it registered.
+    ///
+    /// # Panics
+    ///
+    /// Panics if [`MAX_HANDLERS`] handlers of the kind are already registered.
+    #[must_use = "the handler is removed when the guard is dropped"]
+    pub fn register(self) -> InterruptGuard {
+        let slot = critical_section(|| {
+            // SAFETY: Interrupts are disabled, so the dispatchers don't read them meanwhile.
+            let handlers = unsafe { &mut (*addr_of_mut!(HANDLERS))[self.kind.index()] };
+            let count = unsafe { &mut (*addr_of_mut!(HANDLER_COUNT))[self.kind.index()] };
+            let owned = unsafe { &mut *addr_of_mut!(OWNED_FLAGS) };
+            let Some(slot) = handlers.iter().position(Option::is_none) else {
+                panic!("Interrupt handlers outbounded");
+            };
+            handlers[slot] = Some(self.handler);
+            if *count == 0 {
+                // `add_*` ends with `ei`, so every interrupt is masked until `IME` is restored,
+                // even if this is called from a handler or a critical section.
+                let ie = register::IE.read();
+                register::IE.write(InterruptFlags::new());
+                let dispatcher = self.kind.dispatcher();
+                unsafe {
+                    match self.kind {
+                        InterruptKind::VBlank => gbdk_c::gb::gb::add_VBL(dispatcher),
+                        InterruptKind::LCD => gbdk_c::gb::gb::add_LCD(dispatcher),
+                        InterruptKind::Timer => gbdk_c::gb::gb::add_TIM(dispatcher),
+                        InterruptKind::Serial => gbdk_c::gb::gb::add_SIO(dispatcher),
+                        InterruptKind::Joypad => gbdk_c::gb::gb::add_JOY(dispatcher),
+                    }
+                }
+                restore_ime();
+                if ie.contains(self.kind.flags()) {
+                    register::IE.write(ie);
+                } else {
+                    register::IE.write(ie.union(self.kind.flags()));
+                    *owned = owned.union(self.kind.flags());
+                }
+            }
+            *count += 1;
+            slot
+        });
+        InterruptGuard {
+            interrupt: self,
+            slot: slot as u8,
+        }
+    }
+}
+
+/// Registered interrupt handler, which is removed when dropped.
+pub struct InterruptGuard {
+    interrupt: Interrupt,
+    slot: u8,
+}
+
+impl InterruptGuard {
+    /// The registered interrupt handler.
+    pub fn interrupt(&self) -> Interrupt {
+        self.interrupt
+    }
+
+    /// Keep the handler registered forever.
+    pub fn forget(self) {
+        core::mem::forget(self);
+    }
+}
+
+impl Drop for InterruptGuard {
+    fn drop(&mut self) {
+        let kind = self.interrupt.kind;
+        let last = critical_section(|| {
+            // SAFETY: Interrupts are disabled, so the dispatchers don't read them meanwhile.
+            let handlers = unsafe { &mut (*addr_of_mut!(HANDLERS))[kind.index()] };
+            let count = unsafe { &mut (*addr_of_mut!(HANDLER_COUNT))[kind.index()] };
+            let owned = unsafe { &mut *addr_of_mut!(OWNED_FLAGS) };
+            handlers[self.slot as usize] = None;
+            *count -= 1;
+            if *count == 0 && owned.contains(kind.flags()) {
+                register::IE.apply(|ie| *ie = ie.difference(kind.flags()));
+                *owned = owned.difference(kind.flags());
+            }
+            *count == 0
+        });
+        if last {
+            let dispatcher = kind.dispatcher();
+            critical_section(|| unsafe {
+                match kind {
+                    InterruptKind::VBlank => gbdk_c::gb::gb::remove_VBL(dispatcher),
+                    InterruptKind::LCD => gbdk_c::gb::gb::remove_LCD(dispatcher),
+                    InterruptKind::Timer => gbdk_c::gb::gb::remove_TIM(dispatcher),
+                    InterruptKind::Serial => gbdk_c::gb::gb::remove_SIO(dispatcher),
+                    InterruptKind::Joypad => gbdk_c::gb::gb::remove_JOY(dispatcher),
+                }
+            });
         }
     }
 }
 
-pub struct InterruptBuilder {
-    int_flag: u8,
+/// Disable interrupts again after a critical function of GBDK, which always
+/// enables interrupts at the end.
+fn restore_ime() {
+    if !interrupts_restored() {
+        unsafe { gbdk_c::gb::gb::disable_interrupts() };
+    }
 }
 
-impl InterruptBuilder {
-    pub fn new() -> Self {
-        Self { int_flag: 0x0 }
+/// Whether `IME` should be set now, out of critical sections and handlers.
+fn interrupts_restored() -> bool {
+    // SAFETY: Only written with interrupts disabled.
+    unsafe {
+        !*addr_of_mut!(IN_HANDLER) && *addr_of_mut!(CRITICAL_DEPTH) == 0 && *addr_of_mut!(IME)
     }
+}
 
-    pub unsafe fn register_irq(&mut self, int: Interrupt) -> &mut Self {
-        int.register();
-        self.int_flag |= int.kind as u8;
-        self
+/// Set `IME`, so that interrupts enabled in `IE` are serviced.
+///
+/// In interrupt handlers, it does nothing, as interrupts are enabled again
+/// when the handler returns.
+pub fn enable() {
+    // SAFETY: Handlers don't change it.
+    unsafe {
+        if *addr_of_mut!(IN_HANDLER) {
+            return;
+        }
+        *addr_of_mut!(IME) = true;
+    }
+    if interrupts_restored() {
+        unsafe { gbdk_c::gb::gb::enable_interrupts() };
     }
+}
 
-    pub unsafe fn enable(&self) {
-        gbdk_c::gb::gb::set_interrupts(self.int_flag);
-        gbdk_c::gb::gb::enable_interrupts();
+/// Clear `IME`, so that no interrupt is serviced.
+///
+/// In interrupt handlers, it does nothing, as interrupts are already
+/// disabled, and enabled again when the handler returns.
+pub fn disable() {
+    // SAFETY: Handlers don't change it.
+    unsafe {
+        if *addr_of_mut!(IN_HANDLER) {
+            return;
+        }
+        gbdk_c::gb::gb::disable_interrupts();
+        *addr_of_mut!(IME) = false;
     }
+}
 
-    pub unsafe fn disable(&self) {
+/// Whether `IME` is set, out of [`critical_section`] and interrupt handlers.
+pub fn is_enabled() -> bool {
+    // SAFETY: Handlers don't change it.
+    unsafe { *addr_of_mut!(IME) }
+}
+
+/// Run `f` with interrupts disabled.
+///
+/// The previous state of `IME` is restored afterwards, so critical sections
+/// can be nested, and can be used in interrupt handlers, where interrupts
+/// stay disabled. `IME` is tracked by [`enable`] and [`disable`], so it must
+/// not be changed in other ways.
+pub fn critical_section<R>(f: impl FnOnce() -> R) -> R {
+    // SAFETY: Interrupts are disabled before the depth is changed.
+    unsafe {
         gbdk_c::gb::gb::disable_interrupts();
+        *addr_of_mut!(CRITICAL_DEPTH) += 1;
+    }
+    let result = f();
+    // SAFETY: Interrupts are disabled.
+    unsafe { *addr_of_mut!(CRITICAL_DEPTH) -= 1 };
+    if interrupts_restored() {
+        unsafe { gbdk_c::gb::gb::enable_interrupts() };
     }
+    result
 }
@@ -0,0 +1,123 @@
+//! LCD timing helpers.
+//!
+//! VRAM can't be accessed by the CPU while the PPU is drawing pixels (mode 3).
+//! Writes are ignored and reads return garbage. This module provides the
+//! helpers to wait until VRAM is accessible, or to turn the LCD off for a
+//! bulk transfer.
+//!
+//! Documents are provided by [GB Pan Docs](https://gbdev.io/pandocs/Rendering.html)
+
+use core::ptr::addr_of;
+
+use crate::{
+    gbdk_c::gb::gb::sys_time,
+    mmio,
+    register::{self, Lcdc, PpuMode},
+};
+
+/// First scanline of VBlank.
+pub const VBLANK_LINE: u8 = 144;
+
+/// Whether the LCD is on.
+pub fn is_on() -> bool {
+    register::LCDC.read().lcd_enabled()
+}
+
+/// Number of VBlank interrupts since startup, wrapping around.
+///
+/// It is counted by the VBlank interrupt handler of GBDK, so it doesn't
+/// advance while the VBlank interrupt is disabled or the LCD is off.
+pub fn frame_count() -> u16 {
+    // SAFETY: `sys_time` is only written by the VBlank interrupt handler.
+    let read = || unsafe { addr_of!(sys_time).read_volatile() };
+    // Read until stable, since the interrupt may occur between the two bytes.
+    let mut count = read();
+    loop {
+        let next = read();
+        if next == count {
+            return count;
+        }
+        count = next;
+    }
+}
+
+/// Counts VBlanks elapsed between updates.
+pub(crate) struct FrameTimer {
+    last_count: Option<u16>,
+}
+
+impl FrameTimer {
+    pub(crate) const fn new() -> Self {
+        FrameTimer { last_count: None }
+    }
+
+    pub(crate) fn elapsed(&mut self) -> u16 {
+        let count = frame_count();
+        let elapsed = match self.last_count {
+            Some(last) => count.wrapping_sub(last),
+            None => 0,
+        };
+        self.last_count = Some(count);
+        elapsed
+    }
+}
+
+/// Waits until the PPU enters VBlank.
+///
+/// Returns immediately if the LCD is off.
+pub fn wait_vblank() {
+    while is_on() && mmio::LY.read() < VBLANK_LINE {}
+}
+
+/// Waits until the PPU is in HBlank or VBlank, so that VRAM is accessible for
+/// at least 80 dots (the OAM scan of the next line).
+///
+/// Returns immediately if the LCD is off.
+pub fn wait_vram() {
+    while is_on() {
+        match register::STAT.read().mode() {
+            PpuMode::HBlank | PpuMode::VBlank => return,
+            PpuMode::OamScan | PpuMode::Drawing => {}
+        }
+    }
+}
+
+/// Runs given closure with the LCD turned off, so that VRAM and OAM can be
+/// accessed at any time.
+///
+/// The LCD is turned off at the next VBlank, and turned back on after the
+/// closure if it was on. The screen is blank while the LCD is off.
+///
+/// # Examples
+/// ```
+/// lcd::with_lcd_off(|| {
+///     tiles::load_tiles(0, &TILES);
+///     Background::set_tiles(0, 0, 20, 18, &MAP);
+/// });
+/// ```
+pub fn with_lcd_off<R>(f: impl FnOnce() -> R) -> R {
+    let was_on = is_on();
+    if was_on {
+        wait_vblank();
+        // SAFETY: The LCD is turned off during VBlank.
+        unsafe { register::LCDC.write(register::LCDC.read().with_lcd_enabled(false)) };
+    }
+
+    let result = f();
+
+    if was_on {
+        // SAFETY: Turning on the LCD is safe at any time.
+        unsafe { register::LCDC.write(register::LCDC.read().with_lcd_enabled(true)) };
+    }
+    result
+}
+
+/// Modifies [`register::LCDC`] without changing the LCD enable bit.
+pub(crate) fn update_lcdc(f: impl FnOnce(&mut Lcdc)) {
+    let lcdc = register::LCDC.read();
+    let mut new = lcdc;
+    f(&mut new);
+    // SAFETY: Only turning off the LCD outside of VBlank is unsafe, and the
+    // LCD enable bit is kept.
+    unsafe { register::LCDC.write(new.with_lcd_enabled(lcdc.lcd_enabled())) };
+}
@@ -59,6 +59,30 @@
 //! By default, the Rust-GB compiler contains these files, but when compile GameBoy ROM, it needs
 //! to be copied to the file system. This directory contains those external dependency files.
 //!
+//! If your project has a `music` directory, the song files in it are converted into `out/music.rs`
+//! before the Rust code is compiled. (See `gb::music`, which is a `prototype` feature.)
+//!
+//! ## Banked code and data
+//! Without a memory bank controller, the ROM is limited to 32 KiB. With the `mbc` option of your
+//! ROM package (and the matching `mbc1`, `mbc3` or `mbc5` feature), functions and statics can be
+//! put in switchable ROM banks, by adding ` __banked` to their symbol name.
+//!
+//! ```
+//! #[inline(never)]
+//! #[export_name = "title_screen __banked"]
+//! pub extern "C" fn title_screen() {
+//!     // ...
+//! }
+//!
+//! #[export_name = "LEVEL_1 __banked"]
+//! pub static LEVEL_1: [u8; 4096] = [0; 4096];
+//! ```
+//!
+//! Each of them is split into its own C file, and `bankpack` assigns them to banks. Calls to a
+//! banked function switch to its bank through a trampoline, and back after it returns. A banked
+//! static is read-only, and its bank is in the `__bank_<name>` symbol, to be selected with
+//! `memory::RomBankManager` before reading it.
+//!
 //! ## Execute your ROM
 //! The final result, `out.gb`, is located in the `out` directory. This file can be run using the
 //! GameBoy emulator or real GameBoy (Color / Advance).
@@ -70,15 +94,47 @@
 #![no_std]
 #![no_main]
 #![allow(dead_code)]
+#![cfg_attr(any(doc, feature = "color", feature = "mbc3"), feature(doc_cfg))]
+
+#[cfg(any(
+    all(feature = "mbc1", feature = "mbc3"),
+    all(feature = "mbc1", feature = "mbc5"),
+    all(feature = "mbc3", feature = "mbc5"),
+))]
+compile_error!("Only one of the `mbc1`, `mbc3` and `mbc5` features can be enabled");
 
+pub mod animation;
+pub mod background;
+#[cfg(any(feature = "color", doc))]
+#[doc(cfg(feature = "color"))]
+pub mod cpu;
+#[cfg(any(feature = "color", doc))]
+#[doc(cfg(feature = "color"))]
+pub mod dma;
 pub mod drawing;
 pub mod io;
+pub mod irq;
+pub mod lcd;
+pub mod metasprite;
 pub mod mmio;
+pub mod palette;
+pub mod raster;
+pub mod register;
+#[cfg(any(feature = "mbc3", doc))]
+#[doc(cfg(feature = "mbc3"))]
+pub mod rtc;
+pub mod save;
+pub mod sgb;
+pub mod sound;
+pub mod sprite;
+pub mod system;
+pub mod tiles;
+pub mod window;
 
 pub mod gbdk_c;
 #[cfg(feature = "prototype")]
-pub mod irq;
-#[cfg(feature = "prototype")]
 pub mod memory;
 #[cfg(feature = "prototype")]
+pub mod music;
+#[cfg(feature = "prototype")]
 pub mod time;
@@ -14,11 +14,19 @@
 // NOTE: We want this type to be non exhaustive to force users to only construct it via
 // `Self::new`.
 #[non_exhaustive]
+#[allow(deprecated)]
 pub struct MemoryMap {
     /// The manager for the external RAM banks
     pub external_ram: BankManager,
+    /// The manager for the switchable WRAM banks of the GameBoy Color
+    #[cfg(feature = "color")]
+    pub work_ram: WramBankManager,
+    /// The manager for the switchable ROM banks
+    #[cfg(any(feature = "mbc1", feature = "mbc3", feature = "mbc5"))]
+    pub rom: RomBankManager,
 }
 
+#[allow(deprecated)]
 impl MemoryMap {
     /// This method should be called at the very start of your program in order to set up all the
     /// necessary parts of memory that need to be tracked and initialized.
@@ -26,9 +34,16 @@ impl MemoryMap {
     /// SAFETY:
     /// This function is the first thing you call in your program. Any existing data might get over
     /// overwriten.
+    ///
+    /// With the `color` feature, `work_ram` switches `0xD000..0xE000`, where GBDK puts the stack
+    /// by default. See `WramBankManager::fetch_bank`.
     pub unsafe fn new() -> Self {
         Self {
             external_ram: BankManager::new(),
+            #[cfg(feature = "color")]
+            work_ram: WramBankManager::new(),
+            #[cfg(any(feature = "mbc1", feature = "mbc3", feature = "mbc5"))]
+            rom: RomBankManager::new(),
         }
     }
 }
@@ -37,17 +52,21 @@ impl MemoryMap {
 // assumes you are accessing the external RAM banks
 /// Manages access to a series of RAM banks that overlap in a memory space as well as the register
 /// used to toggle between them.
+///
+/// It doesn't enable cartridge RAM, and `gb::save` selects other banks under a fetched `RamBank`,
+/// so use `gb::save::SramAccess` instead.
+#[deprecated(note = "use `gb::save::SramAccess`, which enables RAM and selects the bank itself")]
 #[non_exhaustive]
 pub struct BankManager;
 
+#[allow(deprecated)]
 impl BankManager {
-    // TODO: This address changes depending on memory bank controller. We need a way to determine,
-    // at compile time, what the controller is.
-    /// The address of the bank that selects the external RAM bank.
-    const BANK_SELECTION_ADDR: u16 = 0x0000;
+    /// The address of the bank that selects the external RAM bank. It is the same for MBC1, MBC3
+    /// and MBC5.
+    const BANK_SELECTION_ADDR: u16 = 0x4000;
 
     /// The address of the start of the switchable RAM banks.
-    const RAM_BANK_START: u16 = 0xB000;
+    const RAM_BANK_START: u16 = 0xA000;
 
     /// This method should only be called by `MemoryMap::new`.
     ///
@@ -91,6 +110,258 @@ impl BankManager {
     }
 }
 
+/// Manages the switchable ROM bank in `0x4000..0x8000`, for the memory bank controller selected by
+/// the `mbc1`, `mbc3` or `mbc5` feature.
+///
+/// | MBC  | Banks       | Registers                                 |
+/// |------|-------------|-------------------------------------------|
+/// | MBC1 | 1 to 31     | `0x2000` (5 bits)                         |
+/// | MBC3 | 1 to 127    | `0x2000` (7 bits)                         |
+/// | MBC5 | 0 to 511    | `0x2000` (lower 8 bits), `0x3000` (bit 8) |
+///
+/// MBC1 can address more banks with the upper bits in `0x4000`, but that register is shared with
+/// the external RAM bank, so it is not used.
+///
+/// The bank is also written to GBDK's `_current_bank` (except for the bit 8 of MBC5), so banked
+/// calls and interrupts switch back to it.
+///
+/// The bank of a banked static is given by the `__bank_<name>` symbol:
+///
+/// ```
+/// extern "C" {
+///     static __bank_LEVEL_1: u8;
+/// }
+///
+/// let bank = unsafe { core::ptr::addr_of!(__bank_LEVEL_1) } as u16;
+/// // SAFETY: Called from bank 0.
+/// let level = unsafe { memory.rom.fetch_bank(bank) };
+/// // SAFETY: The bank of `LEVEL_1` is selected while `level` is alive.
+/// let first = unsafe { core::ptr::read_volatile(&LEVEL_1[0]) };
+/// drop(level); // The previous bank is selected again.
+/// ```
+#[cfg(any(feature = "mbc1", feature = "mbc3", feature = "mbc5"))]
+#[non_exhaustive]
+pub struct RomBankManager;
+
+#[cfg(any(feature = "mbc1", feature = "mbc3", feature = "mbc5"))]
+impl RomBankManager {
+    /// The address of the register that selects the lower 8 bits of the ROM bank.
+    const BANK_SELECTION_ADDR: usize = 0x2000;
+
+    /// The address of the register that selects the bit 8 of the ROM bank. (MBC5)
+    #[cfg(feature = "mbc5")]
+    const BANK_SELECTION_HIGH_ADDR: usize = 0x3000;
+
+    /// The address of the start of the switchable ROM bank.
+    const ROM_BANK_START: usize = 0x4000;
+
+    /// The size of a ROM bank.
+    pub const ROM_BANK_SIZE: usize = 0x4000;
+
+    /// The lowest switchable bank.
+    #[cfg(not(feature = "mbc5"))]
+    pub const MIN_BANK: u16 = 1;
+    /// The lowest switchable bank.
+    #[cfg(feature = "mbc5")]
+    pub const MIN_BANK: u16 = 0;
+
+    /// The highest switchable bank.
+    #[cfg(feature = "mbc1")]
+    pub const MAX_BANK: u16 = 31;
+    /// The highest switchable bank.
+    #[cfg(feature = "mbc3")]
+    pub const MAX_BANK: u16 = 127;
+    /// The highest switchable bank.
+    #[cfg(feature = "mbc5")]
+    pub const MAX_BANK: u16 = 511;
+
+    /// This method should only be called by `MemoryMap::new`.
+    ///
+    /// SAFETY:
+    /// Same safety rules as `MemoryMap::new`
+    unsafe fn new() -> Self {
+        Self
+    }
+
+    unsafe fn select_bank(bank: u16) {
+        crate::gbdk_c::gb::gb::_current_bank = bank as u8;
+        #[cfg(feature = "mbc5")]
+        core::ptr::write_volatile(Self::BANK_SELECTION_HIGH_ADDR as *mut u8, (bank >> 8) as u8);
+        core::ptr::write_volatile(Self::BANK_SELECTION_ADDR as *mut u8, bank as u8);
+    }
+
+    /// The currently selected ROM bank.
+    ///
+    /// The MBC registers are write-only, so it is read from GBDK's `_current_bank`, which only
+    /// has the lower 8 bits.
+    pub fn current_bank(&self) -> u8 {
+        unsafe { crate::gbdk_c::gb::gb::_current_bank }
+    }
+
+    /// This fetches the given ROM bank.
+    ///
+    /// Note that this requires a mutable reference, so the bank can not change while the given
+    /// `RomBank` is out. The previous bank is selected again when it is dropped.
+    ///
+    /// # Safety
+    ///
+    /// This must be called from code in bank 0. Code in the switchable bank, including the banked
+    /// functions calling this one, is switched out along with it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bank` is not from `MIN_BANK` to `MAX_BANK`.
+    pub unsafe fn fetch_bank(&mut self, bank: u16) -> RomBank<'_> {
+        if !(Self::MIN_BANK..=Self::MAX_BANK).contains(&bank) {
+            panic!("ROM bank outbounded");
+        }
+        let previous_bank = self.current_bank();
+        // SAFETY: The caller is in bank 0, so it is not switched out.
+        unsafe { Self::select_bank(bank) };
+        // SAFETY:
+        // The ROM bank is only read through the `RomBank`, which borrows the manager, so the
+        // selected bank stays the same while it is alive.
+        let data = unsafe { &*(Self::ROM_BANK_START as *const [u8; Self::ROM_BANK_SIZE]) };
+        RomBank {
+            bank_number: bank,
+            previous_bank,
+            data,
+        }
+    }
+}
+
+/// A selected ROM bank, mapped at `0x4000..0x8000`.
+#[cfg(any(feature = "mbc1", feature = "mbc3", feature = "mbc5"))]
+pub struct RomBank<'a> {
+    bank_number: u16,
+    previous_bank: u8,
+    data: &'a [u8; RomBankManager::ROM_BANK_SIZE],
+}
+
+#[cfg(any(feature = "mbc1", feature = "mbc3", feature = "mbc5"))]
+impl RomBank<'_> {
+    /// The number of the bank.
+    pub fn bank_number(&self) -> u16 {
+        self.bank_number
+    }
+}
+
+#[cfg(any(feature = "mbc1", feature = "mbc3", feature = "mbc5"))]
+impl core::ops::Deref for RomBank<'_> {
+    type Target = [u8; RomBankManager::ROM_BANK_SIZE];
+
+    fn deref(&self) -> &Self::Target {
+        self.data
+    }
+}
+
+#[cfg(any(feature = "mbc1", feature = "mbc3", feature = "mbc5"))]
+impl Drop for RomBank<'_> {
+    fn drop(&mut self) {
+        // SAFETY:
+        // The bank selected before `fetch_bank` is restored, which also clears the bit 8 of MBC5,
+        // as GBDK's `_current_bank` only has the lower 8 bits.
+        unsafe { RomBankManager::select_bank(self.previous_bank as u16) };
+    }
+}
+
+/// Manages the switchable WRAM banks 1-7 of the GameBoy Color in `0xD000..0xE000`, selected by
+/// the SVBK register.
+///
+/// Like `BankManager`, a fetched bank borrows the manager mutably, so the bank can not change
+/// while a reference into it is alive.
+#[cfg(feature = "color")]
+#[non_exhaustive]
+pub struct WramBankManager;
+
+#[cfg(feature = "color")]
+impl WramBankManager {
+    /// The address of the start of the switchable WRAM banks.
+    const WRAM_BANK_START: usize = 0xD000;
+
+    /// The size of a WRAM bank.
+    pub const WRAM_BANK_SIZE: usize = 0x1000;
+
+    /// This method should only be called by `MemoryMap::new`.
+    ///
+    /// SAFETY:
+    /// Same safety rules as `MemoryMap::new`
+    unsafe fn new() -> Self {
+        Self
+    }
+
+    fn select_bank(&mut self, bank: u8) {
+        crate::register::SVBK.write(crate::register::Svbk::new().with_bank(bank));
+    }
+
+    /// The currently selected WRAM bank, from 1 to 7.
+    pub fn current_bank(&self) -> u8 {
+        // Selecting bank 0 selects bank 1.
+        crate::register::SVBK.read().bank().max(1)
+    }
+
+    /// This fetches the given WRAM bank, from 1 to 7.
+    ///
+    /// Note that this requires a mutable reference, so the bank can not change while the given
+    /// `WramBank` is out.
+    ///
+    /// # Safety
+    ///
+    /// Nothing else in use may live in `0xD000..0xE000`, as it is switched out along with the
+    /// bank. This includes the stack, which GBDK puts at the top of WRAM by default, so the stack
+    /// pointer must be moved below `0xD000` before calling this. Statics must not be placed there
+    /// either.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bank` is not from 1 to 7.
+    pub unsafe fn fetch_bank(&mut self, bank: u8) -> WramBank<'_> {
+        if !(1..=7).contains(&bank) {
+            panic!("WRAM bank outbounded");
+        }
+        self.select_bank(bank);
+        // SAFETY:
+        // The window is only accessed through the `WramBank`, which borrows the manager, so the
+        // selected bank stays the same while it is alive.
+        let data = unsafe { &mut *(Self::WRAM_BANK_START as *mut [u8; Self::WRAM_BANK_SIZE]) };
+        WramBank {
+            bank_number: bank,
+            data,
+        }
+    }
+}
+
+/// A selected WRAM bank, mapped at `0xD000..0xE000`.
+#[cfg(feature = "color")]
+pub struct WramBank<'a> {
+    bank_number: u8,
+    data: &'a mut [u8; WramBankManager::WRAM_BANK_SIZE],
+}
+
+#[cfg(feature = "color")]
+impl WramBank<'_> {
+    /// The number of the bank.
+    pub fn bank_number(&self) -> u8 {
+        self.bank_number
+    }
+}
+
+#[cfg(feature = "color")]
+impl core::ops::Deref for WramBank<'_> {
+    type Target = [u8; WramBankManager::WRAM_BANK_SIZE];
+
+    fn deref(&self) -> &Self::Target {
+        self.data
+    }
+}
+
+#[cfg(feature = "color")]
+impl core::ops::DerefMut for WramBank<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.data
+    }
+}
+
 // I'm not sure what all this would hold... It depends on if this type manages how the bank's
 // memory is allocated.
 struct RamBank {
@@ -0,0 +1,293 @@
+//! Metasprites, characters built from multiple hardware sprites.
+//!
+//! A [`Metasprite`] is a constant list of items, each of them a tile placed at
+//! an offset from the origin of the metasprite. A [`MetaspriteHandle`] owns a
+//! range of hardware sprites and draws a metasprite with them, so the whole
+//! character can be moved, hidden or flipped at once.
+//!
+//! Unlike GBDK's `metasprite_t`, the offset of each item is from the origin,
+//! not from the previous item. When flipped, items are mirrored around the
+//! origin, so it is usually placed at the center of the character.
+//!
+//! # Examples
+//! ```
+//! // 16x16 character made of four 8x8 tiles, with the origin at the center.
+//! const PLAYER: Metasprite = Metasprite::new(&[
+//!     MetaspriteItem::new(-8, -8, 0),
+//!     MetaspriteItem::new(0, -8, 1),
+//!     MetaspriteItem::new(-8, 0, 2),
+//!     MetaspriteItem::new(0, 0, 3),
+//! ]);
+//!
+//! let mut player = MetaspriteHandle::new(PLAYER).unwrap();
+//! player.set_position(80 + sprite::X_OFFSET, 72 + sprite::Y_OFFSET);
+//! player.set_flip(true, false);
+//! ```
+//!
+//! Documents are provided by [GBDK-2020](https://gbdk-2020.github.io/gbdk-2020/docs/api/metasprites_8h.html)
+
+use crate::{
+    register::ObjSize,
+    sprite::{self, OamEntry, ObjAttributes},
+};
+
+/// X flip and Y flip bits of the attributes.
+const FLIP_MASK: u8 = 0b0110_0000;
+
+/// One hardware sprite of a metasprite.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct MetaspriteItem {
+    /// X offset of the left edge from the origin.
+    pub dx: i8,
+    /// Y offset of the top edge from the origin.
+    pub dy: i8,
+    /// Tile index, added to the base tile of the handle.
+    pub tile: u8,
+    /// Attributes, combined with the base attributes of the handle.
+    pub attributes: ObjAttributes,
+}
+
+impl MetaspriteItem {
+    /// Creates an item with cleared attributes.
+    pub const fn new(dx: i8, dy: i8, tile: u8) -> Self {
+        MetaspriteItem {
+            dx,
+            dy,
+            tile,
+            attributes: ObjAttributes::new(),
+        }
+    }
+
+    /// Set the attributes of the item.
+    pub const fn with_attributes(self, attributes: ObjAttributes) -> Self {
+        MetaspriteItem { attributes, ..self }
+    }
+}
+
+/// Constant list of items drawn together.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Metasprite {
+    items: &'static [MetaspriteItem],
+}
+
+impl Metasprite {
+    /// Creates a metasprite from items.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are more than 40 items.
+    pub const fn new(items: &'static [MetaspriteItem]) -> Self {
+        if items.len() > sprite::SPRITE_COUNT as usize {
+            panic!("Metasprite has too many items");
+        }
+        Metasprite { items }
+    }
+
+    /// Items of the metasprite.
+    pub const fn items(&self) -> &'static [MetaspriteItem] {
+        self.items
+    }
+
+    /// Number of hardware sprites used by the metasprite.
+    pub const fn len(&self) -> u8 {
+        self.items.len() as u8
+    }
+
+    /// Whether the metasprite has no item.
+    pub const fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+/// Metasprite drawn with a range of allocated hardware sprites.
+///
+/// The hardware sprites are freed and hidden when it is dropped.
+pub struct MetaspriteHandle {
+    metasprite: Metasprite,
+    first: u8,
+    capacity: u8,
+    x: u8,
+    y: u8,
+    base_tile: u8,
+    base_attributes: ObjAttributes,
+    flip_x: bool,
+    flip_y: bool,
+    hidden: bool,
+}
+
+impl MetaspriteHandle {
+    /// Allocates hardware sprites for a metasprite.
+    ///
+    /// The metasprite is hidden until its position is set. Returns `None` if
+    /// there are not enough consecutive free hardware sprites.
+    pub fn new(metasprite: Metasprite) -> Option<Self> {
+        Self::with_capacity(metasprite, metasprite.len())
+    }
+
+    /// Allocates `capacity` hardware sprites, so that it can be changed to a
+    /// larger metasprite later. (e.g. animation frames)
+    ///
+    /// # Panics
+    ///
+    /// Panics if the metasprite has more items than `capacity`.
+    pub fn with_capacity(metasprite: Metasprite, capacity: u8) -> Option<Self> {
+        if metasprite.len() > capacity {
+            panic!("Metasprite capacity overflowed");
+        }
+        let first = sprite::allocate_range(capacity)?;
+        Some(MetaspriteHandle {
+            metasprite,
+            first,
+            capacity,
+            x: 0,
+            y: 0,
+            base_tile: 0,
+            base_attributes: ObjAttributes::new(),
+            flip_x: false,
+            flip_y: false,
+            hidden: true,
+        })
+    }
+
+    /// Index of the first hardware sprite.
+    pub fn first_index(&self) -> u8 {
+        self.first
+    }
+
+    /// Number of allocated hardware sprites.
+    pub fn capacity(&self) -> u8 {
+        self.capacity
+    }
+
+    /// Current metasprite.
+    pub fn metasprite(&self) -> Metasprite {
+        self.metasprite
+    }
+
+    /// Change the metasprite drawn by the handle.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the metasprite has more items than the capacity.
+    pub fn set_metasprite(&mut self, metasprite: Metasprite) {
+        if metasprite.len() > self.capacity {
+            panic!("Metasprite capacity overflowed");
+        }
+        self.metasprite = metasprite;
+        self.draw();
+    }
+
+    /// Move the origin to the OAM coordinate, and show the metasprite.
+    ///
+    /// The screen coordinate is `(x - 8, y - 16)`. (See [`sprite::X_OFFSET`]
+    /// and [`sprite::Y_OFFSET`])
+    pub fn set_position(&mut self, x: u8, y: u8) {
+        self.x = x;
+        self.y = y;
+        self.hidden = false;
+        self.draw();
+    }
+
+    /// Move the metasprite relatively, wrapping around.
+    pub fn move_by(&mut self, dx: i8, dy: i8) {
+        self.x = self.x.wrapping_add_signed(dx);
+        self.y = self.y.wrapping_add_signed(dy);
+        self.draw();
+    }
+
+    /// OAM coordinate `(x, y)` of the origin.
+    pub fn position(&self) -> (u8, u8) {
+        (self.x, self.y)
+    }
+
+    /// Hide the metasprite.
+    pub fn hide(&mut self) {
+        self.hidden = true;
+        self.draw();
+    }
+
+    /// Show the metasprite at the last position.
+    pub fn show(&mut self) {
+        self.hidden = false;
+        self.draw();
+    }
+
+    /// Whether the metasprite is hidden.
+    pub fn is_hidden(&self) -> bool {
+        self.hidden
+    }
+
+    /// Mirror the metasprite around the origin.
+    pub fn set_flip(&mut self, x: bool, y: bool) {
+        self.flip_x = x;
+        self.flip_y = y;
+        self.draw();
+    }
+
+    /// Whether the metasprite is mirrored `(horizontally, vertically)`.
+    pub fn flip(&self) -> (bool, bool) {
+        (self.flip_x, self.flip_y)
+    }
+
+    /// Set the tile index added to the tile of every item.
+    pub fn set_base_tile(&mut self, tile: u8) {
+        self.base_tile = tile;
+        self.draw();
+    }
+
+    /// Set the attributes combined with the attributes of every item.
+    ///
+    /// The bits are ORed with the item attributes, except the flip bits which
+    /// are toggled.
+    pub fn set_base_attributes(&mut self, attributes: ObjAttributes) {
+        self.base_attributes = attributes;
+        self.draw();
+    }
+
+    fn draw(&self) {
+        let height = match sprite::size() {
+            ObjSize::Size8x8 => 8,
+            ObjSize::Size8x16 => 16,
+        };
+        let items = self.metasprite.items;
+        for i in 0..self.capacity {
+            let item = match items.get(i as usize) {
+                Some(item) if !self.hidden => item,
+                _ => {
+                    sprite::set_entry(self.first + i, OamEntry::HIDDEN);
+                    continue;
+                }
+            };
+
+            let (mut dx, mut dy) = (item.dx as i16, item.dy as i16);
+            let (item_bits, base_bits) = (item.attributes.bits(), self.base_attributes.bits());
+            let mut attributes = ObjAttributes::from_bits(
+                ((item_bits | base_bits) & !FLIP_MASK) | ((item_bits ^ base_bits) & FLIP_MASK),
+            );
+            if self.flip_x {
+                dx = -dx - 8;
+                attributes = attributes.with_x_flip(!attributes.x_flip());
+            }
+            if self.flip_y {
+                dy = -dy - height;
+                attributes = attributes.with_y_flip(!attributes.y_flip());
+            }
+
+            sprite::set_entry(
+                self.first + i,
+                OamEntry {
+                    y: self.y.wrapping_add(dy as u8),
+                    x: self.x.wrapping_add(dx as u8),
+                    tile: self.base_tile.wrapping_add(item.tile),
+                    attributes,
+                },
+            );
+        }
+    }
+}
+
+impl Drop for MetaspriteHandle {
+    fn drop(&mut self) {
+        sprite::free_range(self.first, self.capacity);
+    }
+}
@@ -161,6 +161,15 @@ pub const NR34: VolAddress<u8, Unsafe, Safe> = unsafe { VolAddress::new(0xFF1E)
 /// [Sound channel 4 length timer](https://gbdev.io/pandocs/Audio_Registers.html#ff20--nr41-channel-4-length-timer-write-only)
 pub const NR41: VolAddress<u8, (), Safe> = unsafe { VolAddress::new(0xFF20) };
 
+/// [Sound channel 4 volume & envelope](https://gbdev.io/pandocs/Audio_Registers.html#ff21--nr42-channel-4-volume--envelope)
+pub const NR42: VolAddress<u8, Safe, Safe> = unsafe { VolAddress::new(0xFF21) };
+
+/// [Sound channel 4 frequency & randomness](https://gbdev.io/pandocs/Audio_Registers.html#ff22--nr43-channel-4-frequency--randomness)
+pub const NR43: VolAddress<u8, Safe, Safe> = unsafe { VolAddress::new(0xFF22) };
+
+/// [Sound channel 4 control](https://gbdev.io/pandocs/Audio_Registers.html#ff23--nr44-channel-4-control)
+pub const NR44: VolAddress<u8, Unsafe, Safe> = unsafe { VolAddress::new(0xFF23) };
+
 /// [Master volume & VIN panning](https://gbdev.io/pandocs/Audio_Registers.html#ff24--nr50-master-volume--vin-panning)
 pub const NR50: VolAddress<u8, Safe, Safe> = unsafe { VolAddress::new(0xFF24) };
 
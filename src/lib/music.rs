@@ -0,0 +1,647 @@
+//! Frame-driven music and sound effect player.
+//!
+//! [`MusicPlayer`] is a small tracker-style music driver. It is ticked once per
+//! frame by the VBlank interrupt, and plays a [`Song`] stored in ROM. A song
+//! is a list of patterns (the "order"), each pattern is a list of [`Row`]s, and
+//! each row has one [`Cell`] for each of the four sound channels.
+//!
+//! A [`SoundEffect`] can be played on top of the music. It temporarily steals
+//! one channel from the music, and gives it back when it ends. The music
+//! keeps running silently on the stolen channel, and its current note is
+//! restored afterwards.
+//!
+//! # Song files
+//! Instead of writing a [`Song`] by hand, you can put `*.song` text files in
+//! the `music` directory of your project. `cargo build-rom` converts each of
+//! them into a `pub static` named after the file (in upper case) in
+//! `out/music.rs`, which can be included into your crate.
+//!
+//! ```
+//! mod music {
+//!     include!(concat!(env!("CARGO_MANIFEST_DIR"), "/out/music.rs"));
+//! }
+//!
+//! MusicPlayer::play(&music::THEME); // from `music/theme.song`
+//! ```
+//!
+//! A song file declares the speed, the instruments, the patterns and the
+//! order. Each pattern row has one cell (note, instrument, effect) for each
+//! channel.
+//!
+//! ```text
+//! speed 6
+//!
+//! pulse 0 duty=50 volume=12 envelope=down:2
+//! wave 0 wave=triangle volume=100
+//! noise 0 volume=15 envelope=down:1 shift=4 width=7 divider=2 length=8
+//!
+//! pattern intro
+//! C-4 00 ... | --- .. ... | C-3 00 ... | --- .. ...
+//! E-4 00 101 | G-4 00 ... | --- .. ... | C-4 00 ...
+//! OFF .. ... | OFF .. F03 | OFF .. ... | --- .. ...
+//! end
+//!
+//! order intro intro
+//! ```
+//!
+//! The effects are `1xx` ([`Effect::SlideUp`]), `2xx` ([`Effect::SlideDown`]),
+//! `Bxx` ([`Effect::Jump`]) and `Fxx` ([`Effect::Speed`]), with a hex
+//! parameter.
+//!
+//! # Examples
+//! ```
+//! use gb::music::{Cell, MusicPlayer, Row, Song};
+//! use gb::sound::{Apu, Envelope, Note, Pitch, PulseTone};
+//!
+//! const C: Cell = Cell::note(Note::new(Pitch::C, 4), 0);
+//! const E: Cell = Cell::note(Note::new(Pitch::E, 4), 0);
+//! const PATTERN: [Row; 2] = [
+//!     [C, Cell::EMPTY, Cell::EMPTY, Cell::EMPTY],
+//!     [E, Cell::EMPTY, Cell::EMPTY, Cell::EMPTY],
+//! ];
+//! static SONG: Song = Song {
+//!     speed: 8,
+//!     order: &[&PATTERN],
+//!     pulse_instruments: &[PulseTone::new().with_envelope(Envelope::new().with_volume(10))],
+//!     wave_instruments: &[],
+//!     noise_instruments: &[],
+//! };
+//!
+//! unsafe { MusicPlayer::init(Apu::enable()) }.register().forget();
+//! MusicPlayer::play(&SONG);
+//! ```
+
+use core::ptr::addr_of_mut;
+
+use crate::{
+    irq::{critical_section, Interrupt, InterruptKind},
+    sound::{Apu, NoiseTone, Note, PulseTone, Sweep, WaveTone, Waveform, MAX_PERIOD},
+};
+
+static mut PLAYER: Option<Player> = None;
+
+extern "C" fn tick_player() {
+    // SAFETY: `with_player` masks this interrupt while it uses `PLAYER`.
+    if let Some(player) = unsafe { (*addr_of_mut!(PLAYER)).as_mut() } {
+        player.tick();
+    }
+}
+
+/// Sound channel used by the music and sound effects.
+#[repr(u8)]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    Pulse1 = 0,
+    Pulse2 = 1,
+    Wave = 2,
+    Noise = 3,
+}
+
+impl Channel {
+    const ALL: [Channel; 4] = [
+        Channel::Pulse1,
+        Channel::Pulse2,
+        Channel::Wave,
+        Channel::Noise,
+    ];
+}
+
+/// What a [`Cell`] does to the note of its channel.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Trigger {
+    /// Keep the current note.
+    None,
+    /// Play a new note.
+    ///
+    /// The note is ignored on the noise channel, where the instrument alone
+    /// decides the sound.
+    Note(Note),
+    /// Stop the current note.
+    Off,
+}
+
+/// Effect of a [`Cell`], applied for the duration of its row.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Effect {
+    /// No effect.
+    None,
+    /// Raise the pitch by adding given value to the period every frame.
+    SlideUp(u8),
+    /// Lower the pitch by subtracting given value from the period every frame.
+    SlideDown(u8),
+    /// Change the number of frames per row.
+    Speed(u8),
+    /// Jump to the start of given order after this row.
+    Jump(u8),
+}
+
+/// One channel of a [`Row`].
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Cell {
+    pub trigger: Trigger,
+    /// Index of the instrument for the channel kind. (`pulse_instruments`
+    /// for the pulse channels and so on)
+    pub instrument: u8,
+    pub effect: Effect,
+}
+
+impl Cell {
+    /// A cell that does nothing.
+    pub const EMPTY: Cell = Cell {
+        trigger: Trigger::None,
+        instrument: 0,
+        effect: Effect::None,
+    };
+
+    /// A cell that stops the current note.
+    pub const OFF: Cell = Cell {
+        trigger: Trigger::Off,
+        instrument: 0,
+        effect: Effect::None,
+    };
+
+    /// Creates a cell that plays a note with given instrument.
+    pub const fn note(note: Note, instrument: u8) -> Self {
+        Cell {
+            trigger: Trigger::Note(note),
+            instrument,
+            effect: Effect::None,
+        }
+    }
+
+    /// Set effect of `Cell`.
+    pub const fn with_effect(mut self, effect: Effect) -> Self {
+        self.effect = effect;
+        self
+    }
+}
+
+/// One row of a pattern, in the order of [`Channel`].
+pub type Row = [Cell; 4];
+
+/// Instrument of the wave channel.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct WaveInstrument {
+    pub tone: WaveTone,
+    pub waveform: &'static Waveform,
+}
+
+/// A song to be played by [`MusicPlayer`].
+///
+/// The period of the instrument tones is ignored, the notes of the cells are
+/// used instead. The song loops back to the first order after the last one.
+pub struct Song {
+    /// Number of frames per row.
+    pub speed: u8,
+    /// Patterns in the order they are played.
+    pub order: &'static [&'static [Row]],
+    pub pulse_instruments: &'static [PulseTone],
+    pub wave_instruments: &'static [WaveInstrument],
+    pub noise_instruments: &'static [NoiseTone],
+}
+
+/// One step of a [`SoundEffect`].
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Step<T> {
+    pub tone: T,
+    /// Number of frames until the next step.
+    pub frames: u8,
+}
+
+/// A sound effect, played on one channel on top of the music.
+///
+/// Each step triggers its tone and holds the channel for its frames.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SoundEffect {
+    Pulse1(Sweep, &'static [Step<PulseTone>]),
+    Pulse2(&'static [Step<PulseTone>]),
+    Wave(&'static Waveform, &'static [Step<WaveTone>]),
+    Noise(&'static [Step<NoiseTone>]),
+}
+
+impl SoundEffect {
+    /// The channel this effect steals from the music.
+    pub const fn channel(&self) -> Channel {
+        match self {
+            SoundEffect::Pulse1(..) => Channel::Pulse1,
+            SoundEffect::Pulse2(..) => Channel::Pulse2,
+            SoundEffect::Wave(..) => Channel::Wave,
+            SoundEffect::Noise(..) => Channel::Noise,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Voice {
+    playing: bool,
+    instrument: u8,
+    period: u16,
+    slide: i16,
+}
+
+impl Voice {
+    const SILENT: Voice = Voice {
+        playing: false,
+        instrument: 0,
+        period: 0,
+        slide: 0,
+    };
+}
+
+struct Player {
+    apu: Apu,
+    song: Option<&'static Song>,
+    paused: bool,
+    order: usize,
+    row: usize,
+    tick: u8,
+    speed: u8,
+    jump: Option<usize>,
+    voices: [Voice; 4],
+    loaded_wave: Option<&'static Waveform>,
+    sfx: Option<SoundEffect>,
+    sfx_step: usize,
+    sfx_frames: u8,
+}
+
+impl Player {
+    fn tick(&mut self) {
+        if let Some(song) = self.song {
+            if !self.paused {
+                self.tick_song(song);
+            }
+        }
+        self.tick_sfx();
+    }
+
+    fn tick_song(&mut self, song: &'static Song) {
+        if self.tick == 0 {
+            let row = &song.order[self.order][self.row];
+            for channel in Channel::ALL {
+                self.apply_cell(song, channel, &row[channel as usize]);
+            }
+        } else {
+            for channel in Channel::ALL {
+                self.apply_slide(channel);
+            }
+        }
+
+        self.tick += 1;
+        if self.tick >= self.speed {
+            self.tick = 0;
+            self.row += 1;
+            if let Some(order) = self.jump.take() {
+                self.order = order;
+                self.row = 0;
+            } else if self.row >= song.order[self.order].len() {
+                self.order = (self.order + 1) % song.order.len();
+                self.row = 0;
+            }
+        }
+    }
+
+    fn apply_cell(&mut self, song: &'static Song, channel: Channel, cell: &Cell) {
+        let voice = &mut self.voices[channel as usize];
+        voice.slide = 0;
+        match cell.effect {
+            Effect::None => {}
+            Effect::SlideUp(value) => voice.slide = value as i16,
+            Effect::SlideDown(value) => voice.slide = -(value as i16),
+            Effect::Speed(speed) => self.speed = speed.max(1),
+            Effect::Jump(order) => self.jump = Some(order as usize % song.order.len()),
+        }
+
+        let voice = &mut self.voices[channel as usize];
+        match cell.trigger {
+            Trigger::None => {}
+            Trigger::Note(note) => {
+                voice.playing = true;
+                voice.instrument = cell.instrument;
+                voice.period = match channel {
+                    Channel::Wave => note.wave_period(),
+                    _ => note.period(),
+                };
+                if !self.is_stolen(channel) {
+                    self.trigger(song, channel);
+                }
+            }
+            Trigger::Off => {
+                voice.playing = false;
+                if !self.is_stolen(channel) {
+                    self.silence(channel);
+                }
+            }
+        }
+    }
+
+    fn apply_slide(&mut self, channel: Channel) {
+        let voice = &mut self.voices[channel as usize];
+        if !voice.playing || voice.slide == 0 || channel == Channel::Noise {
+            return;
+        }
+        voice.period = (voice.period as i16 + voice.slide).clamp(0, MAX_PERIOD as i16) as u16;
+        let period = voice.period;
+
+        if self.is_stolen(channel) {
+            return;
+        }
+        match channel {
+            Channel::Pulse1 => self.apu.pulse1.set_period(period),
+            Channel::Pulse2 => self.apu.pulse2.set_period(period),
+            Channel::Wave => self.apu.wave.set_period(period),
+            Channel::Noise => {}
+        }
+    }
+
+    /// Plays the current note of the voice on the channel.
+    ///
+    /// The channel is silenced if the song has no such instrument.
+    fn trigger(&mut self, song: &'static Song, channel: Channel) {
+        let voice = self.voices[channel as usize];
+        let instrument = voice.instrument as usize;
+        match channel {
+            Channel::Pulse1 | Channel::Pulse2 => {
+                let Some(tone) = song.pulse_instruments.get(instrument) else {
+                    return self.silence(channel);
+                };
+                let tone = tone.with_period(voice.period);
+                if channel == Channel::Pulse1 {
+                    self.apu.pulse1.play(&tone);
+                } else {
+                    self.apu.pulse2.play(&tone);
+                }
+            }
+            Channel::Wave => {
+                let Some(instrument) = song.wave_instruments.get(instrument) else {
+                    return self.silence(channel);
+                };
+                self.load_wave(instrument.waveform);
+                self.apu
+                    .wave
+                    .play(&instrument.tone.with_period(voice.period));
+            }
+            Channel::Noise => {
+                let Some(tone) = song.noise_instruments.get(instrument) else {
+                    return self.silence(channel);
+                };
+                self.apu.noise.play(tone);
+            }
+        }
+    }
+
+    fn silence(&mut self, channel: Channel) {
+        match channel {
+            Channel::Pulse1 => self.apu.pulse1.stop(),
+            Channel::Pulse2 => self.apu.pulse2.stop(),
+            Channel::Wave => self.apu.wave.stop(),
+            Channel::Noise => self.apu.noise.stop(),
+        }
+    }
+
+    fn load_wave(&mut self, waveform: &'static Waveform) {
+        let loaded = self
+            .loaded_wave
+            .is_some_and(|loaded| core::ptr::eq(loaded, waveform));
+        if !loaded {
+            self.apu.wave.load(waveform);
+            self.loaded_wave = Some(waveform);
+        }
+    }
+
+    fn is_stolen(&self, channel: Channel) -> bool {
+        self.sfx.is_some_and(|sfx| sfx.channel() == channel)
+    }
+
+    fn start_sfx(&mut self, sfx: SoundEffect) {
+        if let Some(current) = self.sfx {
+            if current.channel() != sfx.channel() {
+                self.release(current.channel());
+            }
+        }
+        if let SoundEffect::Pulse1(sweep, _) = sfx {
+            self.apu.pulse1.set_sweep(sweep);
+        }
+        self.sfx = Some(sfx);
+        self.sfx_step = 0;
+        self.sfx_frames = 0;
+    }
+
+    fn tick_sfx(&mut self) {
+        let Some(sfx) = self.sfx else {
+            return;
+        };
+
+        if self.sfx_frames == 0 {
+            match self.play_sfx_step(sfx) {
+                Some(frames) => {
+                    self.sfx_step += 1;
+                    self.sfx_frames = frames.max(1);
+                }
+                None => {
+                    self.release(sfx.channel());
+                    return;
+                }
+            }
+        }
+        self.sfx_frames -= 1;
+    }
+
+    /// Plays the current step of the sound effect, returning its frames.
+    fn play_sfx_step(&mut self, sfx: SoundEffect) -> Option<u8> {
+        let step = self.sfx_step;
+        match sfx {
+            SoundEffect::Pulse1(_, steps) => steps.get(step).map(|step| {
+                self.apu.pulse1.play(&step.tone);
+                step.frames
+            }),
+            SoundEffect::Pulse2(steps) => steps.get(step).map(|step| {
+                self.apu.pulse2.play(&step.tone);
+                step.frames
+            }),
+            SoundEffect::Wave(waveform, steps) => steps.get(step).map(|step| {
+                self.load_wave(waveform);
+                self.apu.wave.play(&step.tone);
+                step.frames
+            }),
+            SoundEffect::Noise(steps) => steps.get(step).map(|step| {
+                self.apu.noise.play(&step.tone);
+                step.frames
+            }),
+        }
+    }
+
+    /// Gives the channel back to the music, restoring its current note.
+    fn release(&mut self, channel: Channel) {
+        self.sfx = None;
+        if channel == Channel::Pulse1 {
+            self.apu.pulse1.set_sweep(Sweep::new());
+        }
+
+        match self.song {
+            Some(song) if !self.paused && self.voices[channel as usize].playing => {
+                self.trigger(song, channel)
+            }
+            _ => self.silence(channel),
+        }
+    }
+
+    fn stop_song(&mut self) {
+        self.song = None;
+        for channel in Channel::ALL {
+            self.voices[channel as usize] = Voice::SILENT;
+            if !self.is_stolen(channel) {
+                self.silence(channel);
+            }
+        }
+    }
+}
+
+/// Runs given closure with the player, while interrupts are disabled.
+fn with_player<R>(f: impl FnOnce(&mut Player) -> R) -> R {
+    critical_section(|| {
+        // SAFETY: Interrupts are disabled, so the VBlank interrupt, the only
+        // other user of `PLAYER`, doesn't run meanwhile.
+        let player = unsafe { (*addr_of_mut!(PLAYER)).as_mut() };
+        f(player.expect("MusicPlayer is not initialized"))
+    })
+}
+
+/// Tracker-style music and sound effect player.
+///
+/// It must be initialized with [`MusicPlayer::init`], and its interrupt must be
+/// registered with [`Interrupt::register`] before use.
+pub struct MusicPlayer {
+    private: (),
+}
+
+impl MusicPlayer {
+    /// Initializes the player, and returns the VBlank interrupt that ticks it.
+    ///
+    /// The player takes the ownership of the [`Apu`], so every sound should be
+    /// played through the player after this.
+    ///
+    /// # Safety
+    ///
+    /// This must be called before the returned interrupt is registered, and
+    /// only once.
+    pub unsafe fn init(apu: Apu) -> Interrupt {
+        *addr_of_mut!(PLAYER) = Some(Player {
+            apu,
+            song: None,
+            paused: false,
+            order: 0,
+            row: 0,
+            tick: 0,
+            speed: 1,
+            jump: None,
+            voices: [Voice::SILENT; 4],
+            loaded_wave: None,
+            sfx: None,
+            sfx_step: 0,
+            sfx_frames: 0,
+        });
+        Interrupt::new(InterruptKind::VBlank, tick_player)
+    }
+
+    /// Starts playing a song from the beginning.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the player is not initialized, or the song has no order or
+    /// an empty pattern.
+    pub fn play(song: &'static Song) {
+        if song.order.is_empty() {
+            panic!("Song has no order");
+        }
+        if song.order.iter().any(|pattern| pattern.is_empty()) {
+            panic!("Song has an empty pattern");
+        }
+        with_player(|player| {
+            player.stop_song();
+            player.song = Some(song);
+            player.paused = false;
+            player.order = 0;
+            player.row = 0;
+            player.tick = 0;
+            player.speed = song.speed.max(1);
+            player.jump = None;
+        });
+    }
+
+    /// Stops the song. Sound effects keep playing.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the player is not initialized.
+    pub fn stop() {
+        with_player(|player| player.stop_song());
+    }
+
+    /// Pauses the song. Sound effects keep playing.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the player is not initialized.
+    pub fn pause() {
+        with_player(|player| {
+            player.paused = true;
+            for channel in Channel::ALL {
+                if !player.is_stolen(channel) {
+                    player.silence(channel);
+                }
+            }
+        });
+    }
+
+    /// Resumes the paused song from the next row.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the player is not initialized.
+    pub fn resume() {
+        with_player(|player| player.paused = false);
+    }
+
+    /// Whether a song is playing (and not paused).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the player is not initialized.
+    pub fn is_playing() -> bool {
+        with_player(|player| player.song.is_some() && !player.paused)
+    }
+
+    /// Plays a sound effect, stealing its channel from the music.
+    ///
+    /// Only one sound effect plays at a time. A sound effect already playing
+    /// is replaced.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the player is not initialized.
+    pub fn play_sfx(sfx: &SoundEffect) {
+        with_player(|player| player.start_sfx(*sfx));
+    }
+
+    /// Stops the sound effect, giving its channel back to the music.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the player is not initialized.
+    pub fn stop_sfx() {
+        with_player(|player| {
+            if let Some(sfx) = player.sfx {
+                player.release(sfx.channel());
+            }
+        });
+    }
+
+    /// Whether a sound effect is playing.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the player is not initialized.
+    pub fn is_sfx_playing() -> bool {
+        with_player(|player| player.sfx.is_some())
+    }
+}
@@ -0,0 +1,589 @@
+//! DMG palettes, fades and flashes.
+//!
+//! On DMG, each color index of a tile is mapped to one of four shades by the
+//! BGP (background and window), OBP0 and OBP1 (sprites) registers. Fading and
+//! flashing the screen is done by shifting the shades of these palettes.
+//!
+//! [`Fade`] and [`Flash`] are driven by [`crate::lcd::frame_count`], so they can be
+//! updated from the game loop without blocking.
+//!
+//! # Examples
+//! ```
+//! let mut fade = Fade::new(FadeTarget::Black, FadeDirection::Out, 4);
+//! while !fade.update() {
+//!     // game loop
+//! }
+//!
+//! // Or block until the fade finishes.
+//! fade.reverse().run();
+//! ```
+//!
+//! With the `color` feature, [`CgbPalette`] gives access to the 8 BG and 8 OBJ
+//! color palettes of the GameBoy Color. Its colors are [`Rgb555`], which is
+//! also used by the Super GameBoy.
+//!
+//! Documents are provided by [GB Pan Docs](https://gbdev.io/pandocs/Palettes.html)
+
+use crate::{drawing::DmgColor, gbdk_c::gb::gb::vsync, lcd::FrameTimer, mmio, sprite::ObjPalette};
+
+#[cfg(any(feature = "color", doc))]
+#[doc(cfg(feature = "color"))]
+pub use color::{
+    cgb_bg_palette, cgb_obj_palette, set_cgb_bg_palettes, set_cgb_obj_palettes, CgbPalette,
+    CGB_PALETTE_COUNT,
+};
+
+/// Palette of four [`DmgColor`]s, one for each color index.
+#[derive(Clone, Copy, PartialEq)]
+pub struct DmgPalette(pub [DmgColor; 4]);
+
+impl DmgPalette {
+    /// Default palette, from white for color 0 to black for color 3.
+    pub const DEFAULT: DmgPalette = DmgPalette([
+        DmgColor::White,
+        DmgColor::LightGrey,
+        DmgColor::DarkGrey,
+        DmgColor::Black,
+    ]);
+
+    /// Creates a palette from the colors of index 0 to 3.
+    pub const fn new(c0: DmgColor, c1: DmgColor, c2: DmgColor, c3: DmgColor) -> Self {
+        DmgPalette([c0, c1, c2, c3])
+    }
+
+    /// Creates a palette filled with one color.
+    pub const fn filled(color: DmgColor) -> Self {
+        DmgPalette([color; 4])
+    }
+
+    const fn shade(bits: u8) -> DmgColor {
+        match bits & 0b11 {
+            0 => DmgColor::White,
+            1 => DmgColor::LightGrey,
+            2 => DmgColor::DarkGrey,
+            _ => DmgColor::Black,
+        }
+    }
+
+    /// Creates a palette from the value of a palette register.
+    pub const fn from_byte(value: u8) -> Self {
+        DmgPalette([
+            Self::shade(value),
+            Self::shade(value >> 2),
+            Self::shade(value >> 4),
+            Self::shade(value >> 6),
+        ])
+    }
+
+    /// Value of a palette register.
+    pub const fn to_byte(self) -> u8 {
+        (self.0[0] as u8)
+            | ((self.0[1] as u8) << 2)
+            | ((self.0[2] as u8) << 4)
+            | ((self.0[3] as u8) << 6)
+    }
+
+    /// Color of given color index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is greater than 3.
+    pub const fn color(self, index: u8) -> DmgColor {
+        self.0[index as usize]
+    }
+
+    /// Set the color of given color index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is greater than 3.
+    pub const fn with_color(self, index: u8, color: DmgColor) -> Self {
+        let mut colors = self.0;
+        colors[index as usize] = color;
+        DmgPalette(colors)
+    }
+
+    /// Shift every color toward black by `steps` shades.
+    pub const fn darken(self, steps: u8) -> Self {
+        let mut colors = self.0;
+        let mut i = 0;
+        while i < 4 {
+            let shade = (colors[i] as u8).saturating_add(steps);
+            colors[i] = Self::shade(if shade > 3 { 3 } else { shade });
+            i += 1;
+        }
+        DmgPalette(colors)
+    }
+
+    /// Shift every color toward white by `steps` shades.
+    pub const fn lighten(self, steps: u8) -> Self {
+        let mut colors = self.0;
+        let mut i = 0;
+        while i < 4 {
+            colors[i] = Self::shade((colors[i] as u8).saturating_sub(steps));
+            i += 1;
+        }
+        DmgPalette(colors)
+    }
+}
+
+impl From<u8> for DmgPalette {
+    fn from(value: u8) -> Self {
+        DmgPalette::from_byte(value)
+    }
+}
+
+impl From<DmgPalette> for u8 {
+    fn from(value: DmgPalette) -> Self {
+        value.to_byte()
+    }
+}
+
+/// Set the background and window palette. (BGP)
+pub fn set_bg_palette(palette: DmgPalette) {
+    mmio::BGP.write(palette.to_byte());
+}
+
+/// Background and window palette. (BGP)
+pub fn bg_palette() -> DmgPalette {
+    DmgPalette::from_byte(mmio::BGP.read())
+}
+
+/// Set a sprite palette. (OBP0 or OBP1)
+///
+/// Color index 0 of sprites is always transparent.
+pub fn set_obj_palette(obj: ObjPalette, palette: DmgPalette) {
+    match obj {
+        ObjPalette::Obp0 => mmio::OBP0.write(palette.to_byte()),
+        ObjPalette::Obp1 => mmio::OBP1.write(palette.to_byte()),
+    }
+}
+
+/// A sprite palette. (OBP0 or OBP1)
+pub fn obj_palette(obj: ObjPalette) -> DmgPalette {
+    match obj {
+        ObjPalette::Obp0 => DmgPalette::from_byte(mmio::OBP0.read()),
+        ObjPalette::Obp1 => DmgPalette::from_byte(mmio::OBP1.read()),
+    }
+}
+
+/// BGP, OBP0 and OBP1 at once.
+#[derive(Clone, Copy, PartialEq)]
+struct Palettes([DmgPalette; 3]);
+
+impl Palettes {
+    fn read() -> Self {
+        Palettes([
+            bg_palette(),
+            obj_palette(ObjPalette::Obp0),
+            obj_palette(ObjPalette::Obp1),
+        ])
+    }
+
+    fn write(self) {
+        set_bg_palette(self.0[0]);
+        set_obj_palette(ObjPalette::Obp0, self.0[1]);
+        set_obj_palette(ObjPalette::Obp1, self.0[2]);
+    }
+
+    fn shift(self, target: FadeTarget, steps: u8) -> Self {
+        Palettes(self.0.map(|palette| match target {
+            FadeTarget::Black => palette.darken(steps),
+            FadeTarget::White => palette.lighten(steps),
+        }))
+    }
+}
+
+/// Color the screen fades to or from.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FadeTarget {
+    /// Every shade becomes black.
+    Black,
+    /// Every shade becomes white.
+    White,
+}
+
+/// Whether a fade goes to the target color, or back from it.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FadeDirection {
+    /// From the current palettes to the target color.
+    Out,
+    /// From the target color to the current palettes.
+    In,
+}
+
+/// Fade of all three palettes, one shade per step.
+///
+/// The palettes when the fade is created are the palettes of the screen
+/// without fading. To fade in after a fade out, use [`Fade::reverse`] of the
+/// fade out, or set the palettes before creating the fade in. A fade takes 3
+/// steps.
+pub struct Fade {
+    palettes: Palettes,
+    target: FadeTarget,
+    direction: FadeDirection,
+    frames_per_step: u8,
+    step: u8,
+    elapsed: u16,
+    timer: FrameTimer,
+}
+
+impl Fade {
+    /// Number of steps of a fade.
+    pub const STEPS: u8 = 3;
+
+    /// Creates a fade, and applies its first step.
+    ///
+    /// `frames_per_step` is the number of VBlanks of each step.
+    pub fn new(target: FadeTarget, direction: FadeDirection, frames_per_step: u8) -> Self {
+        let fade = Fade {
+            palettes: Palettes::read(),
+            target,
+            direction,
+            frames_per_step: frames_per_step.max(1),
+            step: 0,
+            elapsed: 0,
+            timer: FrameTimer::new(),
+        };
+        fade.apply();
+        fade
+    }
+
+    fn apply(&self) {
+        let shift = match self.direction {
+            FadeDirection::Out => self.step,
+            FadeDirection::In => Self::STEPS - self.step,
+        };
+        self.palettes.shift(self.target, shift).write();
+    }
+
+    /// Creates a fade in the opposite direction with the same palettes, and
+    /// applies its first step.
+    pub fn reverse(&self) -> Self {
+        let direction = match self.direction {
+            FadeDirection::Out => FadeDirection::In,
+            FadeDirection::In => FadeDirection::Out,
+        };
+        let fade = Fade {
+            palettes: self.palettes,
+            target: self.target,
+            direction,
+            frames_per_step: self.frames_per_step,
+            step: 0,
+            elapsed: 0,
+            timer: FrameTimer::new(),
+        };
+        fade.apply();
+        fade
+    }
+
+    /// Whether the fade has finished.
+    pub fn is_finished(&self) -> bool {
+        self.step == Self::STEPS
+    }
+
+    /// Advance the fade by VBlanks elapsed since the last call.
+    ///
+    /// Returns whether the fade has finished.
+    pub fn update(&mut self) -> bool {
+        let elapsed = self.timer.elapsed();
+        self.advance(elapsed)
+    }
+
+    /// Advance the fade by given VBlanks.
+    ///
+    /// Returns whether the fade has finished.
+    pub fn advance(&mut self, frames: u16) -> bool {
+        if self.is_finished() {
+            return true;
+        }
+        self.elapsed = self.elapsed.saturating_add(frames);
+        let steps = (self.elapsed / self.frames_per_step as u16).min(Self::STEPS as u16) as u8;
+        self.elapsed %= self.frames_per_step as u16;
+        if steps > 0 {
+            self.step = (self.step + steps).min(Self::STEPS);
+            self.apply();
+        }
+        self.is_finished()
+    }
+
+    /// Block until the fade finishes.
+    pub fn run(mut self) {
+        while !self.update() {
+            unsafe { vsync() };
+        }
+    }
+}
+
+/// Flash of all three palettes to a color, and back.
+///
+/// The palettes are restored when the flash finishes.
+pub struct Flash {
+    palettes: Palettes,
+    color: DmgColor,
+    frames: u8,
+    remaining: u8,
+    elapsed: u16,
+    timer: FrameTimer,
+}
+
+impl Flash {
+    /// Creates a flash, and applies the flash color.
+    ///
+    /// The screen is filled with `color` for `frames` VBlanks, then restored
+    /// for `frames` VBlanks, `count` times.
+    pub fn new(color: DmgColor, frames: u8, count: u8) -> Self {
+        let flash = Flash {
+            palettes: Palettes::read(),
+            color,
+            frames: frames.max(1),
+            remaining: count.saturating_mul(2),
+            elapsed: 0,
+            timer: FrameTimer::new(),
+        };
+        flash.apply();
+        flash
+    }
+
+    fn apply(&self) {
+        if self.remaining % 2 == 1 || self.remaining == 0 {
+            self.palettes.write();
+        } else {
+            Palettes([DmgPalette::filled(self.color); 3]).write();
+        }
+    }
+
+    /// Whether the flash has finished.
+    pub fn is_finished(&self) -> bool {
+        self.remaining == 0
+    }
+
+    /// Advance the flash by VBlanks elapsed since the last call.
+    ///
+    /// Returns whether the flash has finished.
+    pub fn update(&mut self) -> bool {
+        let elapsed = self.timer.elapsed();
+        self.advance(elapsed)
+    }
+
+    /// Advance the flash by given VBlanks.
+    ///
+    /// Returns whether the flash has finished.
+    pub fn advance(&mut self, frames: u16) -> bool {
+        if self.is_finished() {
+            return true;
+        }
+        self.elapsed = self.elapsed.saturating_add(frames);
+        while self.remaining > 0 && self.elapsed >= self.frames as u16 {
+            self.elapsed -= self.frames as u16;
+            self.remaining -= 1;
+        }
+        self.apply();
+        self.is_finished()
+    }
+
+    /// Stop the flash, restoring the palettes.
+    pub fn stop(&mut self) {
+        self.remaining = 0;
+        self.apply();
+    }
+
+    /// Block until the flash finishes.
+    pub fn run(mut self) {
+        while !self.update() {
+            unsafe { vsync() };
+        }
+    }
+}
+
+/// 15-bit RGB color of the GameBoy Color and the Super GameBoy, 5 bits for
+/// each channel.
+///
+/// # Examples
+/// ```
+/// const SKY: Rgb555 = Rgb555::new(12, 20, 31);
+/// const GRASS: Rgb555 = Rgb555::from_rgb888(0x30, 0xA0, 0x40);
+/// ```
+#[repr(transparent)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Rgb555(u16);
+
+impl Rgb555 {
+    /// Black
+    pub const BLACK: Rgb555 = Rgb555::new(0, 0, 0);
+    /// White
+    pub const WHITE: Rgb555 = Rgb555::new(31, 31, 31);
+
+    /// Creates a color from channels from `0` to `31`.
+    ///
+    /// Only lower 5 bits of each channel are used.
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Rgb555((r as u16 & 0x1F) | ((g as u16 & 0x1F) << 5) | ((b as u16 & 0x1F) << 10))
+    }
+
+    /// Creates a color from 8-bit channels, dropping the lower 3 bits.
+    pub const fn from_rgb888(r: u8, g: u8, b: u8) -> Self {
+        Self::new(r >> 3, g >> 3, b >> 3)
+    }
+
+    /// Creates a color from the value in palette memory.
+    ///
+    /// Bit 15 is ignored.
+    pub const fn from_bits(bits: u16) -> Self {
+        Rgb555(bits & 0x7FFF)
+    }
+
+    /// Value in palette memory.
+    pub const fn bits(self) -> u16 {
+        self.0
+    }
+
+    /// Red channel, from `0` to `31`.
+    pub const fn r(self) -> u8 {
+        (self.0 & 0x1F) as u8
+    }
+
+    /// Green channel, from `0` to `31`.
+    pub const fn g(self) -> u8 {
+        ((self.0 >> 5) & 0x1F) as u8
+    }
+
+    /// Blue channel, from `0` to `31`.
+    pub const fn b(self) -> u8 {
+        ((self.0 >> 10) & 0x1F) as u8
+    }
+}
+
+impl From<u16> for Rgb555 {
+    fn from(value: u16) -> Self {
+        Rgb555::from_bits(value)
+    }
+}
+
+impl From<Rgb555> for u16 {
+    fn from(value: Rgb555) -> Self {
+        value.0
+    }
+}
+
+#[cfg(any(feature = "color", doc))]
+mod color {
+    use voladdress::{Unsafe, VolAddress};
+
+    use super::Rgb555;
+    use crate::{
+        lcd,
+        register::{self, PaletteSpec},
+    };
+
+    /// Number of BG palettes, and OBJ palettes.
+    pub const CGB_PALETTE_COUNT: u8 = 8;
+
+    /// Color palette of the GameBoy Color, one color for each color index.
+    ///
+    /// Color index 0 of OBJ palettes is always transparent.
+    #[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+    pub struct CgbPalette(pub [Rgb555; 4]);
+
+    impl CgbPalette {
+        /// Creates a palette from the colors of index 0 to 3.
+        pub const fn new(c0: Rgb555, c1: Rgb555, c2: Rgb555, c3: Rgb555) -> Self {
+            CgbPalette([c0, c1, c2, c3])
+        }
+    }
+
+    /// Specification and data registers of BG or OBJ palette memory.
+    struct PaletteMemory {
+        spec: VolAddress<PaletteSpec, Unsafe, Unsafe>,
+        data: VolAddress<u8, Unsafe, Unsafe>,
+    }
+
+    const BG: PaletteMemory = PaletteMemory {
+        // SAFETY: BCPS is safe to read and write, so it is also valid as unsafe.
+        spec: unsafe { VolAddress::new(register::BCPS.as_usize()) },
+        data: crate::mmio::BCPD,
+    };
+
+    const OBJ: PaletteMemory = PaletteMemory {
+        spec: register::OCPS,
+        data: crate::mmio::OCPD,
+    };
+
+    impl PaletteMemory {
+        fn write(&self, first: u8, palettes: &[CgbPalette]) {
+            if first as usize + palettes.len() > CGB_PALETTE_COUNT as usize {
+                panic!("CGB palette index outbounded");
+            }
+            let spec = PaletteSpec::new()
+                .with_auto_increment(true)
+                .with_address(first * 8);
+            // SAFETY: The specification register is always accessible.
+            unsafe { self.spec.write(spec) };
+            for color in palettes.iter().flat_map(|palette| palette.0) {
+                for byte in color.bits().to_le_bytes() {
+                    lcd::wait_vram();
+                    // SAFETY: Palette memory is accessible outside of mode 3.
+                    unsafe { self.data.write(byte) };
+                }
+            }
+        }
+
+        fn read(&self, index: u8) -> CgbPalette {
+            if index >= CGB_PALETTE_COUNT {
+                panic!("CGB palette index outbounded");
+            }
+            let mut palette = CgbPalette::default();
+            for (i, color) in palette.0.iter_mut().enumerate() {
+                let mut bytes = [0; 2];
+                for (j, byte) in bytes.iter_mut().enumerate() {
+                    // Reading doesn't increment the address.
+                    let address = index * 8 + i as u8 * 2 + j as u8;
+                    lcd::wait_vram();
+                    // SAFETY: Palette memory is accessible outside of mode 3.
+                    unsafe {
+                        self.spec.write(PaletteSpec::new().with_address(address));
+                        *byte = self.data.read();
+                    }
+                }
+                *color = Rgb555::from_bits(u16::from_le_bytes(bytes));
+            }
+            palette
+        }
+    }
+
+    /// Set BG palettes from `first`, from `0` to `7`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the palettes exceed the 8 BG palettes.
+    pub fn set_cgb_bg_palettes(first: u8, palettes: &[CgbPalette]) {
+        BG.write(first, palettes);
+    }
+
+    /// Set OBJ palettes from `first`, from `0` to `7`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the palettes exceed the 8 OBJ palettes.
+    pub fn set_cgb_obj_palettes(first: u8, palettes: &[CgbPalette]) {
+        OBJ.write(first, palettes);
+    }
+
+    /// Read a BG palette.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is 8 or more.
+    pub fn cgb_bg_palette(index: u8) -> CgbPalette {
+        BG.read(index)
+    }
+
+    /// Read an OBJ palette.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is 8 or more.
+    pub fn cgb_obj_palette(index: u8) -> CgbPalette {
+        OBJ.read(index)
+    }
+}
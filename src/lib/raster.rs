@@ -0,0 +1,280 @@
+//! Raster effects, changing the PPU registers at given scanlines.
+//!
+//! A table of [`RasterEntry`] is walked by the LCD interrupt handler every
+//! frame. Each entry changes a register from its scanline to the end of the
+//! frame, or until another entry changes it again. It enables parallax
+//! scrolling, split screens (a status bar with its own scroll or palette) and
+//! wavy effects.
+//!
+//! The handler is triggered by `LY == LYC` on the line before the entry, and
+//! waits for its HBlank, so that the change applies from the first pixel of
+//! the entry's scanline. Entries of scanline 0 are applied at the start of
+//! VBlank, for the next frame. So the registers should be reset by entries of
+//! scanline 0, to repeat the same effect every frame.
+//!
+//! Each entry takes a part of a scanline in the interrupt handler, so entries
+//! on consecutive scanlines should change only a few registers.
+//!
+//! # Examples
+//! ```
+//! use core::ptr::addr_of_mut;
+//! use gb::lcd;
+//! use gb::raster::{RasterAction, RasterEffects, RasterEntry};
+//!
+//! // Scroll the sky slower than the ground, and keep the status bar fixed.
+//! static mut TABLE: [RasterEntry; 3] = [
+//!     RasterEntry::new(0, RasterAction::Scx(0)),
+//!     RasterEntry::new(48, RasterAction::Scx(0)),
+//!     RasterEntry::new(128, RasterAction::Scx(0)),
+//! ];
+//!
+//! let mut raster = RasterEffects::start(unsafe { &mut *addr_of_mut!(TABLE) });
+//! let mut x: u8 = 0;
+//! loop {
+//!     x = x.wrapping_add(2);
+//!     raster.update(|table| {
+//!         table[0].action = RasterAction::Scx(x / 2);
+//!         table[1].action = RasterAction::Scx(x);
+//!     });
+//!     lcd::wait_vblank();
+//! }
+//! ```
+//!
+//! Documents are provided by [GB Pan Docs](https://gbdev.io/pandocs/STAT.html#ff45--lyc-ly-compare)
+
+use core::ptr::addr_of_mut;
+
+use crate::{
+    irq::{critical_section, Interrupt, InterruptGuard, InterruptKind},
+    lcd::VBLANK_LINE,
+    mmio,
+    palette::DmgPalette,
+    register::{self, Lcdc, PpuMode},
+    window::WX_OFFSET,
+};
+
+static mut TABLE: *mut [RasterEntry] = &mut [];
+static mut INDEX: usize = 0;
+static mut STARTED: bool = false;
+
+/// Register change of a [`RasterEntry`].
+#[derive(Clone, Copy, PartialEq)]
+pub enum RasterAction {
+    /// Background viewport X position.
+    Scx(u8),
+    /// Background viewport Y position.
+    Scy(u8),
+    /// Background and window palette.
+    Bgp(DmgPalette),
+    /// LCD control. The LCD must be kept enabled.
+    Lcdc(Lcdc),
+    /// Screen coordinate `(x, y)` of the window, as
+    /// [`crate::window::Window::set_position`].
+    WindowPosition(u8, u8),
+}
+
+impl RasterAction {
+    fn apply(self) {
+        match self {
+            RasterAction::Scx(x) => mmio::SCX.write(x),
+            RasterAction::Scy(y) => mmio::SCY.write(y),
+            RasterAction::Bgp(palette) => mmio::BGP.write(palette.to_byte()),
+            // SAFETY: The table is validated, so the LCD is kept enabled.
+            RasterAction::Lcdc(lcdc) => unsafe { register::LCDC.write(lcdc) },
+            // SAFETY: The table is validated, so WX is between 7 and 165.
+            RasterAction::WindowPosition(x, y) => unsafe {
+                mmio::WX.write(x + WX_OFFSET);
+                mmio::WY.write(y);
+            },
+        }
+    }
+
+    fn is_valid(self) -> bool {
+        match self {
+            RasterAction::Lcdc(lcdc) => lcdc.lcd_enabled(),
+            RasterAction::WindowPosition(x, y) => x <= 158 && y <= 143,
+            RasterAction::Scx(_) | RasterAction::Scy(_) | RasterAction::Bgp(_) => true,
+        }
+    }
+}
+
+/// Register change at a scanline.
+#[derive(Clone, Copy, PartialEq)]
+pub struct RasterEntry {
+    pub line: u8,
+    pub action: RasterAction,
+}
+
+impl RasterEntry {
+    /// Creates an entry, which applies `action` from `line`.
+    pub const fn new(line: u8, action: RasterAction) -> Self {
+        RasterEntry { line, action }
+    }
+
+    /// Scanline whose `LY == LYC` interrupt applies the entry.
+    const fn trigger_line(self) -> u8 {
+        if self.line == 0 {
+            VBLANK_LINE
+        } else {
+            self.line - 1
+        }
+    }
+}
+
+extern "C" fn apply_raster_entries() {
+    // SAFETY: The table is only accessed by the main thread with interrupts
+    // disabled.
+    let (table, index) = unsafe { (&**addr_of_mut!(TABLE), &mut *addr_of_mut!(INDEX)) };
+    // The LCD interrupt may be requested by other STAT sources.
+    if table.is_empty() || !register::STAT.read().lyc_equal() {
+        return;
+    }
+
+    let trigger = table[*index].trigger_line();
+    if trigger < VBLANK_LINE {
+        while !matches!(register::STAT.read().mode(), PpuMode::HBlank) {}
+    }
+    for _ in 0..table.len() {
+        let entry = table[*index];
+        if entry.trigger_line() != trigger {
+            break;
+        }
+        entry.action.apply();
+        *index = (*index + 1) % table.len();
+    }
+    mmio::LYC.write(table[*index].trigger_line());
+}
+
+/// Raster effects driven by the `LY == LYC` LCD interrupt.
+///
+/// The effects stop when it is dropped.
+pub struct RasterEffects {
+    _interrupt: InterruptGuard,
+}
+
+impl RasterEffects {
+    /// Start walking `table` every frame.
+    ///
+    /// The entries must be sorted by their scanlines. Entries of the same
+    /// scanline are applied in order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the raster effects are already started, or if `table` is
+    /// invalid. (See [`RasterEffects::set_table`])
+    pub fn start(table: &'static mut [RasterEntry]) -> Self {
+        // SAFETY: Only accessed from the main thread.
+        let started = unsafe { &mut *addr_of_mut!(STARTED) };
+        if *started {
+            panic!("Raster effects are already started");
+        }
+        *started = true;
+
+        Self::validate(table);
+        critical_section(|| {
+            Self::walk_from_current_line(table);
+            // SAFETY: Only the `LY == LYC` interrupt source is added.
+            unsafe { register::STAT.write(register::STAT.read().with_lyc_interrupt(true)) };
+            // A request pending from before would call the handler on a wrong line.
+            register::IF.apply(|flags| {
+                flags.set_lcd(false);
+            });
+        });
+        let interrupt = Interrupt::new(InterruptKind::LCD, apply_raster_entries).register();
+        RasterEffects {
+            _interrupt: interrupt,
+        }
+    }
+
+    /// Replace the table, continuing from the current scanline.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the entries are not sorted by their scanlines, if a scanline
+    /// is greater than 143, if an [`RasterAction::Lcdc`] disables the LCD,
+    /// or if a [`RasterAction::WindowPosition`] is out of the screen.
+    pub fn set_table(&mut self, table: &'static mut [RasterEntry]) {
+        Self::validate(table);
+        critical_section(|| Self::walk_from_current_line(table));
+    }
+
+    /// Change the entries of the table, for animated effects such as
+    /// parallax scrolling and waves.
+    ///
+    /// Interrupts are disabled while `f` runs, so it should only change the
+    /// entries, and compute the new values beforehand.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the changed table is invalid. (See [`RasterEffects::set_table`])
+    pub fn update(&mut self, f: impl FnOnce(&mut [RasterEntry])) {
+        critical_section(|| {
+            // SAFETY: Interrupts are disabled, so the handler doesn't read the table.
+            let table = unsafe { &mut **addr_of_mut!(TABLE) };
+            f(table);
+            Self::validate(table);
+            Self::walk_from_current_line(table);
+        });
+    }
+
+    fn validate(table: &[RasterEntry]) {
+        let mut last_line = 0;
+        for entry in table {
+            if entry.line < last_line {
+                panic!("Raster table is not sorted");
+            }
+            if entry.line >= VBLANK_LINE {
+                panic!("Raster line outbounded");
+            }
+            if !entry.action.is_valid() {
+                panic!("Raster action outbounded");
+            }
+            last_line = entry.line;
+        }
+    }
+
+    /// Set the table, and its next entry to the first one triggered after the
+    /// current scanline. Must be called with interrupts disabled.
+    fn walk_from_current_line(table: *mut [RasterEntry]) {
+        // SAFETY: Interrupts are disabled, so the handler doesn't read the table.
+        let entries = unsafe { &*table };
+        // The table is in order of the trigger lines from its first entry
+        // after scanline 0, whose entries are triggered last.
+        let ly = mmio::LY.read();
+        let start = entries
+            .iter()
+            .position(|entry| entry.line != 0)
+            .unwrap_or(0);
+        let mut index = start;
+        for offset in 0..entries.len() {
+            let i = (start + offset) % entries.len();
+            if entries[i].trigger_line() > ly {
+                index = i;
+                break;
+            }
+        }
+        if let Some(entry) = entries.get(index) {
+            mmio::LYC.write(entry.trigger_line());
+        }
+        // SAFETY: Interrupts are disabled, and only accessed from the main thread.
+        unsafe {
+            *addr_of_mut!(TABLE) = table;
+            *addr_of_mut!(INDEX) = index;
+        }
+    }
+}
+
+impl Drop for RasterEffects {
+    fn drop(&mut self) {
+        critical_section(|| {
+            // SAFETY: Only the `LY == LYC` interrupt source is removed.
+            unsafe { register::STAT.write(register::STAT.read().with_lyc_interrupt(false)) };
+            // SAFETY: Interrupts are disabled, and only accessed from the main thread.
+            unsafe {
+                *addr_of_mut!(TABLE) = &mut [];
+                *addr_of_mut!(INDEX) = 0;
+                *addr_of_mut!(STARTED) = false;
+            }
+        });
+    }
+}
@@ -0,0 +1,1042 @@
+//! Typed bitfield wrappers for the [`mmio`] registers.
+//!
+//! Each register of [`mmio`] that packs several fields into one byte has a
+//! wrapper type here, and a typed [`VolAddress`] constant with the same name
+//! and the same read/write permissions as the raw one. Instead of hand-rolling
+//! bit masks from the Pan Docs tables, you can use named getters and setters.
+//!
+//! Every wrapper type provides three kinds of accessors for each field.
+//!
+//! * `field()` : Reads the field.
+//! * `with_field(value)` : Returns a copy with the field changed. It is a
+//!   `const fn`, so it can be used to build values in `const` and `static`.
+//! * `set_field(value)` : Changes the field in place, returning `&mut Self`
+//!   for chaining.
+//!
+//! # Examples
+//!
+//! ```
+//! use gb::register::{self, Tac, TimerClock};
+//!
+//! register::TAC.write(Tac::new().with_enabled(true).with_clock(TimerClock::MCycle256));
+//!
+//! // Modify in place, without touching other bits.
+//! register::IE.apply(|ie| {
+//!     ie.set_vblank(true).set_timer(true);
+//! });
+//! ```
+
+use voladdress::{Safe, Unsafe, VolAddress};
+
+use crate::mmio;
+
+/// Declares a register wrapper type with conversions from and to `u8`.
+macro_rules! register_type {
+    ($(#[$attr:meta])* $name:ident) => {
+        $(#[$attr])*
+        #[repr(transparent)]
+        #[derive(Clone, Copy, PartialEq, Eq, Default)]
+        pub struct $name(u8);
+
+        impl $name {
+            /// Creates a value with all bits cleared.
+            pub const fn new() -> Self {
+                Self(0)
+            }
+
+            /// Creates a value from the raw register byte.
+            pub const fn from_bits(bits: u8) -> Self {
+                Self(bits)
+            }
+
+            /// Returns the raw register byte.
+            pub const fn bits(self) -> u8 {
+                self.0
+            }
+        }
+
+        impl From<u8> for $name {
+            fn from(value: u8) -> Self {
+                Self(value)
+            }
+        }
+
+        impl From<$name> for u8 {
+            fn from(value: $name) -> Self {
+                value.0
+            }
+        }
+    };
+}
+pub(crate) use register_type;
+
+/// Declares getter, const builder and in-place setter of a single bit flag.
+macro_rules! flag {
+    ($(#[$attr:meta])* $get:ident, $with:ident, $set:ident, $bit:literal) => {
+        $(#[$attr])*
+        pub const fn $get(self) -> bool {
+            self.0 & (1 << $bit) != 0
+        }
+
+        #[doc = concat!("Returns a copy with [`Self::", stringify!($get), "`] changed.")]
+        pub const fn $with(self, value: bool) -> Self {
+            if value {
+                Self(self.0 | (1 << $bit))
+            } else {
+                Self(self.0 & !(1 << $bit))
+            }
+        }
+
+        #[doc = concat!("Changes [`Self::", stringify!($get), "`] in place.")]
+        pub fn $set(&mut self, value: bool) -> &mut Self {
+            *self = self.$with(value);
+            self
+        }
+    };
+}
+pub(crate) use flag;
+
+/// Declares getter, const builder and in-place setter of a multi-bit field.
+///
+/// Values wider than the field are truncated.
+macro_rules! field {
+    ($(#[$attr:meta])* $get:ident, $with:ident, $set:ident, $shift:literal, $mask:literal) => {
+        $(#[$attr])*
+        pub const fn $get(self) -> u8 {
+            (self.0 >> $shift) & $mask
+        }
+
+        #[doc = concat!("Returns a copy with [`Self::", stringify!($get), "`] changed.")]
+        pub const fn $with(self, value: u8) -> Self {
+            Self((self.0 & !($mask << $shift)) | ((value & $mask) << $shift))
+        }
+
+        #[doc = concat!("Changes [`Self::", stringify!($get), "`] in place.")]
+        pub fn $set(&mut self, value: u8) -> &mut Self {
+            *self = self.$with(value);
+            self
+        }
+    };
+}
+pub(crate) use field;
+
+/// Declares getter, const builder and in-place setter of a field represented by
+/// a `#[repr(u8)]` enum with a `from_bits` constructor.
+macro_rules! enum_field {
+    ($(#[$attr:meta])* $get:ident, $with:ident, $set:ident, $ty:ty, $shift:literal, $mask:literal) => {
+        $(#[$attr])*
+        pub const fn $get(self) -> $ty {
+            <$ty>::from_bits((self.0 >> $shift) & $mask)
+        }
+
+        #[doc = concat!("Returns a copy with [`Self::", stringify!($get), "`] changed.")]
+        pub const fn $with(self, value: $ty) -> Self {
+            Self((self.0 & !($mask << $shift)) | ((value as u8 & $mask) << $shift))
+        }
+
+        #[doc = concat!("Changes [`Self::", stringify!($get), "`] in place.")]
+        pub fn $set(&mut self, value: $ty) -> &mut Self {
+            *self = self.$with(value);
+            self
+        }
+    };
+}
+pub(crate) use enum_field;
+
+/// Declares a two-variant enum of a single bit field.
+macro_rules! bit_enum {
+    ($(#[$attr:meta])* $name:ident { $(#[$a0:meta])* $v0:ident, $(#[$a1:meta])* $v1:ident $(,)? }) => {
+        $(#[$attr])*
+        #[repr(u8)]
+        #[derive(Clone, Copy, PartialEq, Eq)]
+        pub enum $name {
+            $(#[$a0])*
+            $v0 = 0,
+            $(#[$a1])*
+            $v1 = 1,
+        }
+
+        impl $name {
+            const fn from_bits(bits: u8) -> Self {
+                match bits & 0b1 {
+                    0 => Self::$v0,
+                    _ => Self::$v1,
+                }
+            }
+        }
+    };
+}
+pub(crate) use bit_enum;
+
+/// Declares a four-variant enum of a two bits field.
+macro_rules! two_bit_enum {
+    ($(#[$attr:meta])* $name:ident {
+        $(#[$a0:meta])* $v0:ident,
+        $(#[$a1:meta])* $v1:ident,
+        $(#[$a2:meta])* $v2:ident,
+        $(#[$a3:meta])* $v3:ident $(,)?
+    }) => {
+        $(#[$attr])*
+        #[repr(u8)]
+        #[derive(Clone, Copy, PartialEq, Eq)]
+        pub enum $name {
+            $(#[$a0])*
+            $v0 = 0b00,
+            $(#[$a1])*
+            $v1 = 0b01,
+            $(#[$a2])*
+            $v2 = 0b10,
+            $(#[$a3])*
+            $v3 = 0b11,
+        }
+
+        impl $name {
+            const fn from_bits(bits: u8) -> Self {
+                match bits & 0b11 {
+                    0b00 => Self::$v0,
+                    0b01 => Self::$v1,
+                    0b10 => Self::$v2,
+                    _ => Self::$v3,
+                }
+            }
+        }
+    };
+}
+
+// ---------------------------------------------------------------------------
+// Joypad
+// ---------------------------------------------------------------------------
+
+register_type!(
+    /// Value of the [`JOYP`] (Joypad) register.
+    ///
+    /// The select bits and the key bits are active low: `false` selects a
+    /// line, and a `0` key bit is a pressed key.
+    Joyp
+);
+
+impl Joyp {
+    /// Selects neither the buttons nor the d-pad, so the keys read `$F`.
+    pub const NONE: Self = Self(0x30);
+
+    flag!(
+        /// Buttons are *not* selected.
+        buttons_unselected, with_buttons_unselected, set_buttons_unselected, 5
+    );
+    flag!(
+        /// D-pad is *not* selected.
+        dpad_unselected, with_dpad_unselected, set_dpad_unselected, 4
+    );
+
+    /// Key bits of the selected lines, `0` for pressed keys. (Read-only)
+    pub const fn keys(self) -> u8 {
+        self.0 & 0x0F
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Timer, serial and interrupts
+// ---------------------------------------------------------------------------
+
+register_type!(
+    /// Value of the [`TAC`] (Timer control) register.
+    Tac
+);
+
+two_bit_enum!(
+    /// Frequency at which [`mmio::TIMA`] is incremented.
+    ///
+    /// The frequencies are of DMG and CGB in single-speed mode.
+    /// They are doubled in CGB double-speed mode.
+    TimerClock {
+        /// Increment every 256 M-cycles (4096 Hz)
+        MCycle256,
+        /// Increment every 4 M-cycles (262144 Hz)
+        MCycle4,
+        /// Increment every 16 M-cycles (65536 Hz)
+        MCycle16,
+        /// Increment every 64 M-cycles (16384 Hz)
+        MCycle64,
+    }
+);
+
+impl TimerClock {
+    /// Frequency of the clock in Hz, in single-speed mode.
+    pub const fn frequency(self) -> u32 {
+        match self {
+            TimerClock::MCycle256 => 4096,
+            TimerClock::MCycle4 => 262144,
+            TimerClock::MCycle16 => 65536,
+            TimerClock::MCycle64 => 16384,
+        }
+    }
+}
+
+impl Tac {
+    flag!(
+        /// Whether [`mmio::TIMA`] is incremented.
+        enabled, with_enabled, set_enabled, 2
+    );
+    enum_field!(
+        /// Frequency at which [`mmio::TIMA`] is incremented.
+        clock, with_clock, set_clock, TimerClock, 0, 0b11
+    );
+}
+
+register_type!(
+    /// Value of the [`IE`] (Interrupt enable) and [`IF`] (Interrupt flag)
+    /// registers.
+    InterruptFlags
+);
+
+impl InterruptFlags {
+    /// All five interrupts.
+    pub const ALL: Self = Self(0x1F);
+
+    flag!(
+        /// VBlank interrupt.
+        vblank, with_vblank, set_vblank, 0
+    );
+    flag!(
+        /// LCD (STAT) interrupt.
+        lcd, with_lcd, set_lcd, 1
+    );
+    flag!(
+        /// Timer interrupt.
+        timer, with_timer, set_timer, 2
+    );
+    flag!(
+        /// Serial interrupt.
+        serial, with_serial, set_serial, 3
+    );
+    flag!(
+        /// Joypad interrupt.
+        joypad, with_joypad, set_joypad, 4
+    );
+
+    /// Returns a union of both flags.
+    pub const fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    /// Returns `self` without the flags set in `other`.
+    pub const fn difference(self, other: Self) -> Self {
+        Self(self.0 & !other.0)
+    }
+
+    /// Whether all flags set in `other` are also set in `self`.
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+register_type!(
+    /// Value of the [`SC`] (Serial transfer control) register.
+    SerialControl
+);
+
+impl SerialControl {
+    flag!(
+        /// Transfer requested or in progress.
+        transfer, with_transfer, set_transfer, 7
+    );
+    flag!(
+        /// High speed serial clock. (CGB mode only)
+        high_speed, with_high_speed, set_high_speed, 1
+    );
+    flag!(
+        /// Use the internal clock. (This GameBoy is the master)
+        internal_clock, with_internal_clock, set_internal_clock, 0
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Audio
+// ---------------------------------------------------------------------------
+
+bit_enum!(
+    /// Direction of the channel 1 period sweep.
+    SweepDirection {
+        /// Period increases, so the pitch goes down.
+        Increase,
+        /// Period decreases, so the pitch goes up.
+        Decrease,
+    }
+);
+
+register_type!(
+    /// Value of the [`NR10`] (Sound channel 1 sweep) register.
+    Sweep
+);
+
+impl Sweep {
+    field!(
+        /// Sweep pace, in 128 Hz ticks. `0` disables the sweep.
+        pace, with_pace, set_pace, 4, 0b111
+    );
+    enum_field!(
+        /// Direction of the sweep.
+        direction, with_direction, set_direction, SweepDirection, 3, 0b1
+    );
+    field!(
+        /// Individual step. The period changes by `period / 2^step` each tick.
+        step, with_step, set_step, 0, 0b111
+    );
+}
+
+two_bit_enum!(
+    /// Waveform duty cycle of the pulse channels.
+    DutyCycle {
+        /// 12.5% high
+        Eighth,
+        /// 25% high
+        Quarter,
+        /// 50% high
+        Half,
+        /// 75% high
+        ThreeQuarters,
+    }
+);
+
+register_type!(
+    /// Value of the [`NR11`] and [`NR21`] (length timer & duty cycle) registers.
+    LengthDuty
+);
+
+impl LengthDuty {
+    enum_field!(
+        /// Waveform duty cycle.
+        duty, with_duty, set_duty, DutyCycle, 6, 0b11
+    );
+    field!(
+        /// Initial length timer. The channel stops after `64 - length` 256 Hz
+        /// ticks if length is enabled. (Write-only)
+        length, with_length, set_length, 0, 0b111111
+    );
+}
+
+bit_enum!(
+    /// Direction of the volume envelope.
+    EnvelopeDirection {
+        /// Volume decreases over time.
+        Decrease,
+        /// Volume increases over time.
+        Increase,
+    }
+);
+
+register_type!(
+    /// Value of the [`NR12`], [`NR22`] and [`NR42`] (volume & envelope)
+    /// registers.
+    ///
+    /// Setting both the initial volume and the direction to `0`
+    /// ([`EnvelopeDirection::Decrease`]) turns the channel DAC off.
+    Envelope
+);
+
+impl Envelope {
+    field!(
+        /// Initial volume, from `0` to `15`.
+        volume, with_volume, set_volume, 4, 0b1111
+    );
+    enum_field!(
+        /// Direction of the envelope.
+        direction, with_direction, set_direction, EnvelopeDirection, 3, 0b1
+    );
+    field!(
+        /// Envelope pace, in 64 Hz ticks. `0` disables the envelope.
+        pace, with_pace, set_pace, 0, 0b111
+    );
+
+    /// Whether the channel DAC is on with this envelope.
+    pub const fn dac_enabled(self) -> bool {
+        self.0 & 0xF8 != 0
+    }
+}
+
+register_type!(
+    /// Value of the [`NR14`], [`NR24`], [`NR34`] and [`NR44`] (period high &
+    /// control) registers.
+    ///
+    /// [`NR44`] has no period bits, so [`Self::period_high`] is ignored there.
+    PeriodControl
+);
+
+impl PeriodControl {
+    flag!(
+        /// Triggers the channel. (Write-only)
+        trigger, with_trigger, set_trigger, 7
+    );
+    flag!(
+        /// Stops the channel when the length timer expires.
+        length_enabled, with_length_enabled, set_length_enabled, 6
+    );
+    field!(
+        /// Upper 3 bits of the 11-bit period. (Write-only)
+        period_high, with_period_high, set_period_high, 0, 0b111
+    );
+}
+
+register_type!(
+    /// Value of the [`NR30`] (Sound channel 3 DAC enable) register.
+    WaveDac
+);
+
+impl WaveDac {
+    flag!(
+        /// Whether the channel 3 DAC is on.
+        enabled, with_enabled, set_enabled, 7
+    );
+}
+
+two_bit_enum!(
+    /// Output level of the sound channel 3.
+    WaveVolume {
+        /// Mute
+        Mute,
+        /// 100% volume
+        Full,
+        /// 50% volume
+        Half,
+        /// 25% volume
+        Quarter,
+    }
+);
+
+register_type!(
+    /// Value of the [`NR32`] (Sound channel 3 output level) register.
+    WaveOutputLevel
+);
+
+impl WaveOutputLevel {
+    enum_field!(
+        /// Output level.
+        volume, with_volume, set_volume, WaveVolume, 5, 0b11
+    );
+}
+
+bit_enum!(
+    /// Width of the noise channel LFSR.
+    LfsrWidth {
+        /// 15 bits, white noise.
+        Bits15,
+        /// 7 bits, more regular "metallic" noise.
+        Bits7,
+    }
+);
+
+register_type!(
+    /// Value of the [`NR43`] (Sound channel 4 frequency & randomness) register.
+    NoiseControl
+);
+
+impl NoiseControl {
+    field!(
+        /// Clock shift.
+        clock_shift, with_clock_shift, set_clock_shift, 4, 0b1111
+    );
+    enum_field!(
+        /// Width of the LFSR.
+        width, with_width, set_width, LfsrWidth, 3, 0b1
+    );
+    field!(
+        /// Clock divider. `0` is treated as `0.5`.
+        divider, with_divider, set_divider, 0, 0b111
+    );
+}
+
+register_type!(
+    /// Value of the [`NR50`] (Master volume & VIN panning) register.
+    MasterVolume
+);
+
+impl MasterVolume {
+    flag!(
+        /// Mix VIN into the left output.
+        vin_left, with_vin_left, set_vin_left, 7
+    );
+    field!(
+        /// Left output volume, from `0` (very quiet) to `7` (no reduction).
+        left, with_left, set_left, 4, 0b111
+    );
+    flag!(
+        /// Mix VIN into the right output.
+        vin_right, with_vin_right, set_vin_right, 3
+    );
+    field!(
+        /// Right output volume, from `0` (very quiet) to `7` (no reduction).
+        right, with_right, set_right, 0, 0b111
+    );
+}
+
+register_type!(
+    /// Value of the [`NR51`] (Sound panning) register.
+    SoundPanning
+);
+
+impl SoundPanning {
+    /// Every channel to both outputs.
+    pub const CENTER: Self = Self(0xFF);
+
+    flag!(
+        /// Channel 4 to the left output.
+        ch4_left, with_ch4_left, set_ch4_left, 7
+    );
+    flag!(
+        /// Channel 3 to the left output.
+        ch3_left, with_ch3_left, set_ch3_left, 6
+    );
+    flag!(
+        /// Channel 2 to the left output.
+        ch2_left, with_ch2_left, set_ch2_left, 5
+    );
+    flag!(
+        /// Channel 1 to the left output.
+        ch1_left, with_ch1_left, set_ch1_left, 4
+    );
+    flag!(
+        /// Channel 4 to the right output.
+        ch4_right, with_ch4_right, set_ch4_right, 3
+    );
+    flag!(
+        /// Channel 3 to the right output.
+        ch3_right, with_ch3_right, set_ch3_right, 2
+    );
+    flag!(
+        /// Channel 2 to the right output.
+        ch2_right, with_ch2_right, set_ch2_right, 1
+    );
+    flag!(
+        /// Channel 1 to the right output.
+        ch1_right, with_ch1_right, set_ch1_right, 0
+    );
+}
+
+register_type!(
+    /// Value of the [`NR52`] (Audio master control) register.
+    AudioControl
+);
+
+impl AudioControl {
+    flag!(
+        /// Whether the APU is powered on. Turning it off clears all sound
+        /// registers.
+        enabled, with_enabled, set_enabled, 7
+    );
+    flag!(
+        /// Whether channel 4 is active. (Read-only)
+        ch4_on, with_ch4_on, set_ch4_on, 3
+    );
+    flag!(
+        /// Whether channel 3 is active. (Read-only)
+        ch3_on, with_ch3_on, set_ch3_on, 2
+    );
+    flag!(
+        /// Whether channel 2 is active. (Read-only)
+        ch2_on, with_ch2_on, set_ch2_on, 1
+    );
+    flag!(
+        /// Whether channel 1 is active. (Read-only)
+        ch1_on, with_ch1_on, set_ch1_on, 0
+    );
+}
+
+// ---------------------------------------------------------------------------
+// LCD
+// ---------------------------------------------------------------------------
+
+bit_enum!(
+    /// Area of VRAM used for a tile map.
+    TileMapArea {
+        /// `$9800-$9BFF`
+        Area9800,
+        /// `$9C00-$9FFF`
+        Area9C00,
+    }
+);
+
+impl TileMapArea {
+    /// Start address of the tile map.
+    pub const fn address(self) -> usize {
+        match self {
+            TileMapArea::Area9800 => 0x9800,
+            TileMapArea::Area9C00 => 0x9C00,
+        }
+    }
+}
+
+bit_enum!(
+    /// Area of VRAM used for the BG and Window tile data.
+    TileDataArea {
+        /// `$8800-$97FF`, tile IDs are signed, with `0` at `$9000`.
+        Area8800,
+        /// `$8000-$8FFF`, tile IDs are unsigned. (Same as OBJ tile data)
+        Area8000,
+    }
+);
+
+bit_enum!(
+    /// Size of every OBJ (sprite).
+    ObjSize {
+        /// 8x8 pixels
+        Size8x8,
+        /// 8x16 pixels
+        Size8x16,
+    }
+);
+
+register_type!(
+    /// Value of the [`LCDC`] (LCD control) register.
+    Lcdc
+);
+
+impl Lcdc {
+    flag!(
+        /// LCD & PPU enable.
+        ///
+        /// Stopping the LCD outside of VBlank may damage the hardware.
+        lcd_enabled, with_lcd_enabled, set_lcd_enabled, 7
+    );
+    enum_field!(
+        /// Window tile map area.
+        window_map, with_window_map, set_window_map, TileMapArea, 6, 0b1
+    );
+    flag!(
+        /// Window enable.
+        window_enabled, with_window_enabled, set_window_enabled, 5
+    );
+    enum_field!(
+        /// BG & Window tile data area.
+        tile_data, with_tile_data, set_tile_data, TileDataArea, 4, 0b1
+    );
+    enum_field!(
+        /// BG tile map area.
+        bg_map, with_bg_map, set_bg_map, TileMapArea, 3, 0b1
+    );
+    enum_field!(
+        /// OBJ size.
+        obj_size, with_obj_size, set_obj_size, ObjSize, 2, 0b1
+    );
+    flag!(
+        /// OBJ enable.
+        obj_enabled, with_obj_enabled, set_obj_enabled, 1
+    );
+    flag!(
+        /// BG & Window enable. (On CGB, BG & Window master priority)
+        bg_enabled, with_bg_enabled, set_bg_enabled, 0
+    );
+}
+
+two_bit_enum!(
+    /// Current mode of the PPU.
+    PpuMode {
+        /// Mode 0, HBlank. VRAM and OAM are accessible.
+        HBlank,
+        /// Mode 1, VBlank. VRAM and OAM are accessible.
+        VBlank,
+        /// Mode 2, searching OAM. VRAM is accessible.
+        OamScan,
+        /// Mode 3, drawing pixels. Neither VRAM nor OAM is accessible.
+        Drawing,
+    }
+);
+
+register_type!(
+    /// Value of the [`STAT`] (LCD status) register.
+    Stat
+);
+
+impl Stat {
+    flag!(
+        /// Request LCD interrupt when `LY == LYC`.
+        lyc_interrupt, with_lyc_interrupt, set_lyc_interrupt, 6
+    );
+    flag!(
+        /// Request LCD interrupt on mode 2 (OAM scan).
+        oam_interrupt, with_oam_interrupt, set_oam_interrupt, 5
+    );
+    flag!(
+        /// Request LCD interrupt on mode 1 (VBlank).
+        vblank_interrupt, with_vblank_interrupt, set_vblank_interrupt, 4
+    );
+    flag!(
+        /// Request LCD interrupt on mode 0 (HBlank).
+        hblank_interrupt, with_hblank_interrupt, set_hblank_interrupt, 3
+    );
+
+    /// Whether `LY == LYC`. (Read-only)
+    pub const fn lyc_equal(self) -> bool {
+        self.0 & (1 << 2) != 0
+    }
+
+    /// Current PPU mode. (Read-only)
+    pub const fn mode(self) -> PpuMode {
+        PpuMode::from_bits(self.0)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// GameBoy Color
+// ---------------------------------------------------------------------------
+
+#[cfg(any(feature = "color", doc))]
+#[doc(cfg(feature = "color"))]
+pub use color::{Hdma5, Key1, Opri, PaletteSpec, Pcm, Rp, Svbk, Vbk};
+
+#[cfg(any(feature = "color", doc))]
+mod color {
+    register_type!(
+        /// Value of the [`super::KEY1`] (Prepare speed switch) register.
+        Key1
+    );
+
+    impl Key1 {
+        /// Whether the CPU is in double speed mode. (Read-only)
+        pub const fn double_speed(self) -> bool {
+            self.0 & (1 << 7) != 0
+        }
+
+        flag!(
+            /// Whether the speed switch is armed.
+            armed, with_armed, set_armed, 0
+        );
+    }
+
+    register_type!(
+        /// Value of the [`super::BCPS`] and [`super::OCPS`] (Color palette specification)
+        /// registers.
+        PaletteSpec
+    );
+
+    impl PaletteSpec {
+        flag!(
+            /// Increment the address after each write to the data register.
+            auto_increment, with_auto_increment, set_auto_increment, 7
+        );
+        field!(
+            /// Byte address in the palette memory, from `0` to `63`.
+            address, with_address, set_address, 0, 0b111111
+        );
+    }
+
+    register_type!(
+        /// Value of the [`super::VBK`] (VRAM bank) register.
+        Vbk
+    );
+
+    impl Vbk {
+        field!(
+            /// Selected VRAM bank, `0` or `1`.
+            bank, with_bank, set_bank, 0, 0b1
+        );
+    }
+
+    register_type!(
+        /// Value of the [`super::HDMA5`] (VRAM DMA length/mode/start) register.
+        Hdma5
+    );
+
+    impl Hdma5 {
+        flag!(
+            /// When written, HBlank DMA instead of general-purpose DMA. When
+            /// read, no HBlank DMA is active.
+            hblank_or_inactive, with_hblank_or_inactive, set_hblank_or_inactive, 7
+        );
+        field!(
+            /// Number of 16 bytes blocks minus 1. When read during HBlank DMA,
+            /// the remaining blocks minus 1.
+            blocks, with_blocks, set_blocks, 0, 0b111_1111
+        );
+    }
+
+    register_type!(
+        /// Value of the [`super::RP`] (Infrared communications port) register.
+        Rp
+    );
+
+    impl Rp {
+        field!(
+            /// Data read enable, `0b11` to enable and `0b00` to disable.
+            read_enable, with_read_enable, set_read_enable, 6, 0b11
+        );
+
+        /// Whether no light is received. (Read-only)
+        pub const fn not_receiving(self) -> bool {
+            self.0 & (1 << 1) != 0
+        }
+
+        flag!(
+            /// Emit infrared light.
+            emitting, with_emitting, set_emitting, 0
+        );
+    }
+
+    register_type!(
+        /// Value of the [`super::OPRI`] (Object priority mode) register.
+        Opri
+    );
+
+    impl Opri {
+        flag!(
+            /// Priority by X coordinate like DMG, instead of OAM position.
+            coordinate_priority, with_coordinate_priority, set_coordinate_priority, 0
+        );
+    }
+
+    register_type!(
+        /// Value of the [`super::SVBK`] (WRAM bank) register.
+        Svbk
+    );
+
+    impl Svbk {
+        field!(
+            /// Selected WRAM bank at `$D000-$DFFF`, from `1` to `7`. `0`
+            /// selects bank 1.
+            bank, with_bank, set_bank, 0, 0b111
+        );
+    }
+
+    register_type!(
+        /// Value of the [`super::PCM12`] and [`super::PCM34`] (Audio digital
+        /// outputs) registers.
+        Pcm
+    );
+
+    impl Pcm {
+        /// Digital output of channel 1 (PCM12) or 3 (PCM34). (Read-only)
+        pub const fn low_channel(self) -> u8 {
+            self.0 & 0x0F
+        }
+
+        /// Digital output of channel 2 (PCM12) or 4 (PCM34). (Read-only)
+        pub const fn high_channel(self) -> u8 {
+            self.0 >> 4
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Typed registers
+// ---------------------------------------------------------------------------
+
+/// Typed [`mmio::JOYP`]
+///
+/// # Safety
+/// See [`mmio::JOYP`].
+pub const JOYP: VolAddress<Joyp, Safe, Unsafe> = unsafe { mmio::JOYP.cast() };
+
+/// Typed [`mmio::SC`]
+pub const SC: VolAddress<SerialControl, Safe, Safe> = unsafe { mmio::SC.cast() };
+
+/// Typed [`mmio::TAC`]
+pub const TAC: VolAddress<Tac, Safe, Safe> = unsafe { mmio::TAC.cast() };
+
+/// Typed [`mmio::IF`]
+pub const IF: VolAddress<InterruptFlags, Safe, Safe> = unsafe { mmio::IF.cast() };
+
+/// Typed [`mmio::NR10`]
+pub const NR10: VolAddress<Sweep, Safe, Safe> = unsafe { mmio::NR10.cast() };
+
+/// Typed [`mmio::NR11`]
+pub const NR11: VolAddress<LengthDuty, Unsafe, Safe> = unsafe { mmio::NR11.cast() };
+
+/// Typed [`mmio::NR12`]
+pub const NR12: VolAddress<Envelope, Safe, Safe> = unsafe { mmio::NR12.cast() };
+
+/// Typed [`mmio::NR14`]
+pub const NR14: VolAddress<PeriodControl, Unsafe, Safe> = unsafe { mmio::NR14.cast() };
+
+/// Typed [`mmio::NR21`]
+pub const NR21: VolAddress<LengthDuty, Unsafe, Safe> = unsafe { mmio::NR21.cast() };
+
+/// Typed [`mmio::NR22`]
+pub const NR22: VolAddress<Envelope, Safe, Safe> = unsafe { mmio::NR22.cast() };
+
+/// Typed [`mmio::NR24`]
+pub const NR24: VolAddress<PeriodControl, Unsafe, Safe> = unsafe { mmio::NR24.cast() };
+
+/// Typed [`mmio::NR30`]
+pub const NR30: VolAddress<WaveDac, Safe, Safe> = unsafe { mmio::NR30.cast() };
+
+/// Typed [`mmio::NR32`]
+pub const NR32: VolAddress<WaveOutputLevel, Safe, Safe> = unsafe { mmio::NR32.cast() };
+
+/// Typed [`mmio::NR34`]
+pub const NR34: VolAddress<PeriodControl, Unsafe, Safe> = unsafe { mmio::NR34.cast() };
+
+/// Typed [`mmio::NR42`]
+pub const NR42: VolAddress<Envelope, Safe, Safe> = unsafe { mmio::NR42.cast() };
+
+/// Typed [`mmio::NR43`]
+pub const NR43: VolAddress<NoiseControl, Safe, Safe> = unsafe { mmio::NR43.cast() };
+
+/// Typed [`mmio::NR44`]
+pub const NR44: VolAddress<PeriodControl, Unsafe, Safe> = unsafe { mmio::NR44.cast() };
+
+/// Typed [`mmio::NR50`]
+pub const NR50: VolAddress<MasterVolume, Safe, Safe> = unsafe { mmio::NR50.cast() };
+
+/// Typed [`mmio::NR51`]
+pub const NR51: VolAddress<SoundPanning, Safe, Safe> = unsafe { mmio::NR51.cast() };
+
+/// Typed [`mmio::NR52`]
+pub const NR52: VolAddress<AudioControl, Safe, Safe> = unsafe { mmio::NR52.cast() };
+
+/// Typed [`mmio::LCDC`]
+pub const LCDC: VolAddress<Lcdc, Safe, Unsafe> = unsafe { mmio::LCDC.cast() };
+
+/// Typed [`mmio::STAT`]
+pub const STAT: VolAddress<Stat, Safe, Unsafe> = unsafe { mmio::STAT.cast() };
+
+/// Typed [`mmio::KEY1`]
+#[cfg(any(feature = "color", doc))]
+#[doc(cfg(feature = "color"))]
+pub const KEY1: VolAddress<Key1, Safe, Unsafe> = unsafe { mmio::KEY1.cast() };
+
+/// Typed [`mmio::VBK`]
+#[cfg(any(feature = "color", doc))]
+#[doc(cfg(feature = "color"))]
+pub const VBK: VolAddress<Vbk, Safe, Safe> = unsafe { mmio::VBK.cast() };
+
+/// Typed [`mmio::HDMA5`]
+#[cfg(any(feature = "color", doc))]
+#[doc(cfg(feature = "color"))]
+pub const HDMA5: VolAddress<Hdma5, Safe, Unsafe> = unsafe { mmio::HDMA5.cast() };
+
+/// Typed [`mmio::RP`]
+#[cfg(any(feature = "color", doc))]
+#[doc(cfg(feature = "color"))]
+pub const RP: VolAddress<Rp, Safe, Unsafe> = unsafe { mmio::RP.cast() };
+
+/// Typed [`mmio::BCPS`]
+#[cfg(any(feature = "color", doc))]
+#[doc(cfg(feature = "color"))]
+pub const BCPS: VolAddress<PaletteSpec, Safe, Safe> = unsafe { mmio::BCPS.cast() };
+
+/// Typed [`mmio::OCPS`]
+#[cfg(any(feature = "color", doc))]
+#[doc(cfg(feature = "color"))]
+pub const OCPS: VolAddress<PaletteSpec, Unsafe, Unsafe> = unsafe { mmio::OCPS.cast() };
+
+/// Typed [`mmio::OPRI`]
+#[cfg(any(feature = "color", doc))]
+#[doc(cfg(feature = "color"))]
+pub const OPRI: VolAddress<Opri, Unsafe, Unsafe> = unsafe { mmio::OPRI.cast() };
+
+/// Typed [`mmio::SVBK`]
+#[cfg(any(feature = "color", doc))]
+#[doc(cfg(feature = "color"))]
+pub const SVBK: VolAddress<Svbk, Safe, Safe> = unsafe { mmio::SVBK.cast() };
+
+/// Typed [`mmio::PCM12`]
+#[cfg(any(feature = "color", doc))]
+#[doc(cfg(feature = "color"))]
+pub const PCM12: VolAddress<Pcm, Safe, ()> = unsafe { mmio::PCM12.cast() };
+
+/// Typed [`mmio::PCM34`]
+#[cfg(any(feature = "color", doc))]
+#[doc(cfg(feature = "color"))]
+pub const PCM34: VolAddress<Pcm, Safe, ()> = unsafe { mmio::PCM34.cast() };
+
+/// Typed [`mmio::IE`]
+pub const IE: VolAddress<InterruptFlags, Safe, Safe> = unsafe { mmio::IE.cast() };
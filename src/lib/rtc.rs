@@ -0,0 +1,237 @@
+//! Real time clock of MBC3 cartridges.
+//!
+//! The MBC3 has a clock that keeps running on the cartridge battery, even
+//! while the GameBoy is off. It counts seconds, minutes, hours and up to 512
+//! days, and sets the day carry flag when the day counter overflows.
+//!
+//! Unlike `gb::time::Instant`, which is counted by timer interrupts, it
+//! is accurate enough for long durations, such as the time passed since the
+//! last play.
+//!
+//! The clock registers are read from a latched copy, so that they don't
+//! change while being read. [`RealTimeClock::read`] latches the clock first.
+//!
+//! The cartridge type must be MBC3 with a timer (`0x0F` or `0x10`), set by
+//! `mbc = "mbc3"`, `rtc = true` and `battery = true` in
+//! `[package.metadata.rust-gb]` of `Cargo.toml`.
+//!
+//! # Examples
+//! ```
+//! use gb::rtc::RealTimeClock;
+//!
+//! let clock = RealTimeClock::new();
+//! let now = clock.elapsed();
+//! let away = now.saturating_sub(LAST_PLAYED);
+//! if away.as_secs() > 24 * 60 * 60 {
+//!     // A day passed
+//! }
+//! ```
+//!
+//! Documents are provided by [GB Pan Docs](https://gbdev.io/pandocs/MBC3.html#the-clock-counter-registers)
+
+use core::time::Duration;
+
+use crate::save::SramAccess;
+
+/// Latch clock data register of the MBC3. On MBC1, it is the banking mode
+/// register instead, so this module is only built with the `mbc3` feature,
+/// which can't be enabled with `mbc1`.
+const LATCH_ADDR: usize = 0x6000;
+
+/// RTC register numbers, selected in place of RAM banks.
+const RTC_S: u8 = 0x08;
+const RTC_M: u8 = 0x09;
+const RTC_H: u8 = 0x0A;
+const RTC_DL: u8 = 0x0B;
+const RTC_DH: u8 = 0x0C;
+
+/// Bits of the RTC_DH register.
+const DH_DAY_BIT8: u8 = 0b0000_0001;
+const DH_HALT: u8 = 0b0100_0000;
+const DH_CARRY: u8 = 0b1000_0000;
+
+/// Number of days the day counter can count.
+pub const DAY_COUNT: u16 = 512;
+
+/// Value of the clock counters.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct RtcTime {
+    /// Seconds, from 0 to 59.
+    pub seconds: u8,
+    /// Minutes, from 0 to 59.
+    pub minutes: u8,
+    /// Hours, from 0 to 23.
+    pub hours: u8,
+    /// Days, from 0 to 511.
+    pub days: u16,
+}
+
+impl RtcTime {
+    /// Creates a time from a duration, dropping the fractions of a second.
+    ///
+    /// Returns `None` if the duration is 512 days or longer.
+    pub const fn from_duration(duration: Duration) -> Option<Self> {
+        let secs = duration.as_secs();
+        let days = secs / 86400;
+        if days >= DAY_COUNT as u64 {
+            return None;
+        }
+        Some(RtcTime {
+            seconds: (secs % 60) as u8,
+            minutes: (secs / 60 % 60) as u8,
+            hours: (secs / 3600 % 24) as u8,
+            days: days as u16,
+        })
+    }
+
+    /// Duration counted by the clock.
+    pub const fn as_duration(self) -> Duration {
+        Duration::from_secs(
+            self.days as u64 * 86400
+                + self.hours as u64 * 3600
+                + self.minutes as u64 * 60
+                + self.seconds as u64,
+        )
+    }
+}
+
+impl From<RtcTime> for Duration {
+    fn from(value: RtcTime) -> Self {
+        value.as_duration()
+    }
+}
+
+/// Real time clock of the MBC3.
+///
+/// It is only a handle, so it can be created anywhere with
+/// [`RealTimeClock::new`]. Changing the clock takes `&mut self`.
+pub struct RealTimeClock {
+    private: (),
+}
+
+impl RealTimeClock {
+    /// Creates a handle of the clock.
+    ///
+    /// The cartridge must have the MBC3 timer, as described in the module
+    /// documentation.
+    pub const fn new() -> Self {
+        RealTimeClock { private: () }
+    }
+
+    fn read_register(access: &SramAccess, register: u8) -> u8 {
+        access.read(register, 0)
+    }
+
+    fn write_register(access: &SramAccess, register: u8, value: u8) {
+        access.write(register, 0, value)
+    }
+
+    /// Copy the clock counters into the registers, to be read.
+    pub fn latch(&self) {
+        // SAFETY: Writing 0 then 1 to the MBC3 register only latches the clock.
+        unsafe {
+            core::ptr::write_volatile(LATCH_ADDR as *mut u8, 0);
+            core::ptr::write_volatile(LATCH_ADDR as *mut u8, 1);
+        }
+    }
+
+    /// Latch and read the clock.
+    pub fn read(&self) -> RtcTime {
+        let access = SramAccess::enable();
+        self.latch();
+        let dh = Self::read_register(&access, RTC_DH);
+        RtcTime {
+            seconds: Self::read_register(&access, RTC_S) & 0x3F,
+            minutes: Self::read_register(&access, RTC_M) & 0x3F,
+            hours: Self::read_register(&access, RTC_H) & 0x1F,
+            days: Self::read_register(&access, RTC_DL) as u16 | (((dh & DH_DAY_BIT8) as u16) << 8),
+        }
+    }
+
+    /// Duration counted by the clock.
+    ///
+    /// It wraps around after 512 days, see [`RealTimeClock::day_carry`].
+    pub fn elapsed(&self) -> Duration {
+        self.read().as_duration()
+    }
+
+    /// Set the clock counters.
+    ///
+    /// The clock is halted while it is written, and the sub-second counter
+    /// is reset. The halt and day carry flags are kept.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a counter is out of its range.
+    pub fn write(&mut self, time: RtcTime) {
+        if time.seconds > 59 || time.minutes > 59 || time.hours > 23 || time.days >= DAY_COUNT {
+            panic!("RTC time outbounded");
+        }
+        let access = SramAccess::enable();
+        self.latch();
+        let dh = Self::read_register(&access, RTC_DH);
+        Self::write_register(&access, RTC_DH, dh | DH_HALT);
+
+        Self::write_register(&access, RTC_S, time.seconds);
+        Self::write_register(&access, RTC_M, time.minutes);
+        Self::write_register(&access, RTC_H, time.hours);
+        Self::write_register(&access, RTC_DL, time.days as u8);
+        let day_bit8 = (time.days >> 8) as u8 & DH_DAY_BIT8;
+        Self::write_register(&access, RTC_DH, (dh & !DH_DAY_BIT8) | DH_HALT | day_bit8);
+        Self::write_register(&access, RTC_DH, (dh & !DH_DAY_BIT8) | day_bit8);
+    }
+
+    /// Set the clock to zero.
+    pub fn reset(&mut self) {
+        self.write(RtcTime::default());
+    }
+
+    fn update_dh(&mut self, f: impl FnOnce(u8) -> u8) {
+        let access = SramAccess::enable();
+        self.latch();
+        let dh = Self::read_register(&access, RTC_DH);
+        Self::write_register(&access, RTC_DH, f(dh));
+    }
+
+    /// Whether the clock is halted.
+    pub fn is_halted(&self) -> bool {
+        let access = SramAccess::enable();
+        self.latch();
+        Self::read_register(&access, RTC_DH) & DH_HALT != 0
+    }
+
+    /// Halt or resume the clock.
+    pub fn set_halted(&mut self, halted: bool) {
+        self.update_dh(|dh| if halted { dh | DH_HALT } else { dh & !DH_HALT });
+    }
+
+    /// Whether the day counter overflowed since the flag was cleared.
+    ///
+    /// The flag stays set until [`RealTimeClock::clear_day_carry`] is called.
+    pub fn day_carry(&self) -> bool {
+        let access = SramAccess::enable();
+        self.latch();
+        Self::read_register(&access, RTC_DH) & DH_CARRY != 0
+    }
+
+    /// Clear the day carry flag.
+    pub fn clear_day_carry(&mut self) {
+        self.update_dh(|dh| dh & !DH_CARRY);
+    }
+}
+
+impl Default for RealTimeClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Latch and read the clock. (See [`RealTimeClock::read`])
+pub fn read() -> RtcTime {
+    RealTimeClock::new().read()
+}
+
+/// Duration counted by the clock. (See [`RealTimeClock::elapsed`])
+pub fn elapsed() -> Duration {
+    RealTimeClock::new().elapsed()
+}
@@ -0,0 +1,374 @@
+//! Battery-backed cartridge RAM (SRAM) saves.
+//!
+//! Cartridge RAM is mapped at `$A000-$BFFF`, 8 KiB for each bank, and it must
+//! be enabled before access. It is disabled again as soon as possible, so
+//! that it is not corrupted if the power is turned off.
+//!
+//! A [`SaveSlot`] stores a [`SaveData`] value with a header, so that a first
+//! boot (no save yet), a corrupted save and an old version of the save can be
+//! told apart.
+//!
+//! | Offset | Size | Content                             |
+//! |--------|------|-------------------------------------|
+//! | 0      | 4    | Magic number `GBSV`                 |
+//! | 4      | 1    | Version of the data                 |
+//! | 5      | 2    | Length of the data                  |
+//! | 7      | 2    | Checksum of the version and data    |
+//! | 9      | -    | Data                                |
+//!
+//! The cartridge header must declare RAM and a battery, with `ram-size` and
+//! `battery = true` in `[package.metadata.rust-gb]` of `Cargo.toml`. On MBC1,
+//! the RAM bank register is shared with the upper bits of the ROM bank, so
+//! only RAM bank 0 should be used.
+//!
+//! # Examples
+//! ```
+//! #[derive(Clone, Copy)]
+//! #[repr(C)]
+//! struct Progress {
+//!     level: u8,
+//!     lives: u8,
+//!     score: u16,
+//! }
+//!
+//! // SAFETY: Integers only, without padding.
+//! unsafe impl SaveData for Progress {
+//!     const VERSION: u8 = 2;
+//!
+//!     fn migrate(version: u8, old: SavedBytes) -> Option<Self> {
+//!         match version {
+//!             // Version 1 had no score.
+//!             1 => Some(Progress { level: old.get(0)?, lives: old.get(1)?, score: 0 }),
+//!             _ => None,
+//!         }
+//!     }
+//! }
+//!
+//! const SLOTS: [SaveSlot<Progress>; 3] = [
+//!     SaveSlot::new(0, 0x000),
+//!     SaveSlot::new(0, 0x100),
+//!     SaveSlot::new(0, 0x200),
+//! ];
+//!
+//! let progress = match SLOTS[0].load() {
+//!     Ok(progress) => progress,
+//!     Err(SaveError::Empty) => Progress { level: 1, lives: 3, score: 0 },
+//!     Err(_) => panic!("broken save"),
+//! };
+//! SLOTS[0].save(&progress);
+//! ```
+//!
+//! Documents are provided by [GB Pan Docs](https://gbdev.io/pandocs/MBC1.html#00001fff--ram-enable-write-only)
+
+use core::{marker::PhantomData, mem::size_of, mem::MaybeUninit, ptr::addr_of_mut};
+
+use crate::irq::critical_section;
+
+/// Start of cartridge RAM.
+pub const SRAM_START: usize = 0xA000;
+
+/// Size of a cartridge RAM bank.
+pub const SRAM_BANK_SIZE: usize = 0x2000;
+
+/// Size of the header of a [`SaveSlot`].
+pub const HEADER_SIZE: usize = 9;
+
+/// Magic number at the start of a saved slot.
+const MAGIC: [u8; 4] = *b"GBSV";
+
+/// RAM enable register of MBC1, MBC3 and MBC5.
+const RAM_ENABLE_ADDR: usize = 0x0000;
+
+/// RAM bank number register of MBC1, MBC3 and MBC5.
+const RAM_BANK_ADDR: usize = 0x4000;
+
+const RAM_ENABLE: u8 = 0x0A;
+const RAM_DISABLE: u8 = 0x00;
+
+static mut ENABLE_COUNT: u8 = 0;
+
+/// Access to cartridge RAM, which is enabled while it is alive.
+///
+/// Accesses can be nested, and RAM is disabled when the last one is dropped.
+/// Each access selects the RAM bank it reads or writes, and the bank is not
+/// restored afterwards.
+pub struct SramAccess {
+    private: (),
+}
+
+impl SramAccess {
+    /// Enable cartridge RAM.
+    pub fn enable() -> Self {
+        critical_section(|| {
+            // SAFETY: Interrupts are disabled, so handlers can't access it meanwhile.
+            unsafe {
+                let count = &mut *addr_of_mut!(ENABLE_COUNT);
+                if *count == 0 {
+                    core::ptr::write_volatile(RAM_ENABLE_ADDR as *mut u8, RAM_ENABLE);
+                }
+                *count += 1;
+            }
+        });
+        SramAccess { private: () }
+    }
+
+    fn address(bank: u8, offset: usize) -> *mut u8 {
+        if offset >= SRAM_BANK_SIZE {
+            panic!("SRAM offset outbounded");
+        }
+        // SAFETY: Writing the MBC register only selects the RAM bank.
+        unsafe { core::ptr::write_volatile(RAM_BANK_ADDR as *mut u8, bank) };
+        (SRAM_START + offset) as *mut u8
+    }
+
+    /// Read a byte of given bank.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `offset` is out of the bank.
+    pub fn read(&self, bank: u8, offset: usize) -> u8 {
+        // SAFETY: Cartridge RAM is enabled.
+        unsafe { core::ptr::read_volatile(Self::address(bank, offset)) }
+    }
+
+    /// Write a byte of given bank.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `offset` is out of the bank.
+    pub fn write(&self, bank: u8, offset: usize, value: u8) {
+        // SAFETY: Cartridge RAM is enabled.
+        unsafe { core::ptr::write_volatile(Self::address(bank, offset), value) }
+    }
+}
+
+impl Drop for SramAccess {
+    fn drop(&mut self) {
+        critical_section(|| {
+            // SAFETY: Interrupts are disabled, so handlers can't access it meanwhile.
+            unsafe {
+                let count = &mut *addr_of_mut!(ENABLE_COUNT);
+                *count -= 1;
+                if *count == 0 {
+                    core::ptr::write_volatile(RAM_ENABLE_ADDR as *mut u8, RAM_DISABLE);
+                }
+            }
+        });
+    }
+}
+
+/// A value that can be saved in a [`SaveSlot`].
+///
+/// # Safety
+///
+/// `Self` must have no padding bytes, and every bit pattern must be a valid
+/// value of it. (Like integers, and arrays and `#[repr(C)]` structs of them)
+pub unsafe trait SaveData: Copy {
+    /// Version of the data, saved with it.
+    ///
+    /// Increase it when the layout of `Self` changes.
+    const VERSION: u8;
+
+    /// Converts a save of an older (or newer) version.
+    ///
+    /// Returns `None` if the version is not supported, which is the default.
+    fn migrate(version: u8, old: SavedBytes) -> Option<Self> {
+        let _ = (version, old);
+        None
+    }
+}
+
+/// Data of a save in cartridge RAM, given to [`SaveData::migrate`].
+pub struct SavedBytes<'a> {
+    access: &'a SramAccess,
+    bank: u8,
+    offset: usize,
+    len: usize,
+}
+
+impl SavedBytes<'_> {
+    /// Length of the data.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the data is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Byte at `index`, or `None` if it is out of the data.
+    pub fn get(&self, index: usize) -> Option<u8> {
+        if index >= self.len {
+            return None;
+        }
+        Some(self.access.read(self.bank, self.offset + index))
+    }
+
+    /// Reads a value at `index`, or `None` if it is out of the data.
+    pub fn read<U: SaveData>(&self, index: usize) -> Option<U> {
+        if index + size_of::<U>() > self.len {
+            return None;
+        }
+        Some(read_value(self.access, self.bank, self.offset + index))
+    }
+}
+
+/// Error of loading a save.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SaveError {
+    /// Nothing is saved in the slot, such as on first boot.
+    Empty,
+    /// The checksum doesn't match the data.
+    Corrupted,
+    /// The save is of a version that [`SaveData::migrate`] doesn't support.
+    UnsupportedVersion(u8),
+}
+
+fn read_value<T: SaveData>(access: &SramAccess, bank: u8, offset: usize) -> T {
+    let mut value = MaybeUninit::<T>::uninit();
+    let bytes = value.as_mut_ptr() as *mut u8;
+    for i in 0..size_of::<T>() {
+        // SAFETY: `i` is in `value`.
+        unsafe { bytes.add(i).write(access.read(bank, offset + i)) };
+    }
+    // SAFETY: Every byte is written, and every bit pattern is valid for `SaveData`.
+    unsafe { value.assume_init() }
+}
+
+/// Fletcher-16 checksum.
+struct Checksum {
+    sum1: u16,
+    sum2: u16,
+}
+
+impl Checksum {
+    fn new() -> Self {
+        Checksum { sum1: 0, sum2: 0 }
+    }
+
+    fn add(&mut self, byte: u8) {
+        self.sum1 = (self.sum1 + byte as u16) % 255;
+        self.sum2 = (self.sum2 + self.sum1) % 255;
+    }
+
+    fn finish(&self) -> u16 {
+        (self.sum2 << 8) | self.sum1
+    }
+}
+
+/// A slot storing a [`SaveData`] value in cartridge RAM.
+///
+/// A slot takes [`HEADER_SIZE`] bytes and the size of `T`. Leave room between
+/// slots if `T` may grow in a later version.
+#[derive(Clone, Copy)]
+pub struct SaveSlot<T: SaveData> {
+    bank: u8,
+    offset: usize,
+    _marker: PhantomData<T>,
+}
+
+impl<T: SaveData> SaveSlot<T> {
+    /// Creates a slot at `offset` of RAM bank `bank`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the slot doesn't fit in the bank.
+    pub const fn new(bank: u8, offset: usize) -> Self {
+        if offset + HEADER_SIZE + size_of::<T>() > SRAM_BANK_SIZE {
+            panic!("Save slot outbounded");
+        }
+        SaveSlot {
+            bank,
+            offset,
+            _marker: PhantomData,
+        }
+    }
+
+    fn header(&self, access: &SramAccess) -> Option<(u8, usize, u16)> {
+        let read = |i| access.read(self.bank, self.offset + i);
+        if (0..MAGIC.len()).any(|i| read(i) != MAGIC[i]) {
+            return None;
+        }
+        let version = read(4);
+        let len = u16::from_le_bytes([read(5), read(6)]) as usize;
+        let checksum = u16::from_le_bytes([read(7), read(8)]);
+        Some((version, len, checksum))
+    }
+
+    fn checksum(&self, access: &SramAccess, version: u8, len: usize) -> u16 {
+        let mut checksum = Checksum::new();
+        checksum.add(version);
+        for i in 0..len {
+            checksum.add(access.read(self.bank, self.offset + HEADER_SIZE + i));
+        }
+        checksum.finish()
+    }
+
+    /// Loads the saved value, converting it with [`SaveData::migrate`] if it
+    /// is of another version.
+    pub fn load(&self) -> Result<T, SaveError> {
+        let access = SramAccess::enable();
+        let (version, len, checksum) = self.header(&access).ok_or(SaveError::Empty)?;
+        if self.offset + HEADER_SIZE + len > SRAM_BANK_SIZE
+            || self.checksum(&access, version, len) != checksum
+        {
+            return Err(SaveError::Corrupted);
+        }
+
+        if version == T::VERSION && len == size_of::<T>() {
+            return Ok(read_value(&access, self.bank, self.offset + HEADER_SIZE));
+        }
+        let old = SavedBytes {
+            access: &access,
+            bank: self.bank,
+            offset: self.offset + HEADER_SIZE,
+            len,
+        };
+        T::migrate(version, old).ok_or(SaveError::UnsupportedVersion(version))
+    }
+
+    /// Saves a value.
+    ///
+    /// The slot is marked empty while the data is written, so a save that is
+    /// interrupted by a power loss is loaded as [`SaveError::Empty`].
+    pub fn save(&self, value: &T) {
+        let access = SramAccess::enable();
+        self.erase_with(&access);
+
+        let bytes = value as *const T as *const u8;
+        for i in 0..size_of::<T>() {
+            // SAFETY: `i` is in `value`, which has no padding bytes.
+            let byte = unsafe { *bytes.add(i) };
+            access.write(self.bank, self.offset + HEADER_SIZE + i, byte);
+        }
+
+        let checksum = self.checksum(&access, T::VERSION, size_of::<T>());
+        let [len_low, len_high] = (size_of::<T>() as u16).to_le_bytes();
+        let [checksum_low, checksum_high] = checksum.to_le_bytes();
+        let header = [T::VERSION, len_low, len_high, checksum_low, checksum_high];
+        for (i, byte) in header.iter().enumerate() {
+            access.write(self.bank, self.offset + MAGIC.len() + i, *byte);
+        }
+        // The magic number is written last, to mark the slot valid.
+        for (i, byte) in MAGIC.iter().enumerate() {
+            access.write(self.bank, self.offset + i, *byte);
+        }
+    }
+
+    fn erase_with(&self, access: &SramAccess) {
+        for i in 0..MAGIC.len() {
+            access.write(self.bank, self.offset + i, 0);
+        }
+    }
+
+    /// Marks the slot empty.
+    pub fn erase(&self) {
+        self.erase_with(&SramAccess::enable());
+    }
+
+    /// Whether nothing is saved in the slot. A corrupted save is not empty.
+    pub fn is_empty(&self) -> bool {
+        self.header(&SramAccess::enable()).is_none()
+    }
+}
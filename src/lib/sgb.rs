@@ -0,0 +1,446 @@
+//! Super GameBoy packets and borders.
+//!
+//! The Super GameBoy (SGB) receives 16-byte command packets, sent bit by bit
+//! through the [`JOYP`](crate::mmio::JOYP) register. Some commands (like
+//! CHR_TRN and PCT_TRN for borders) also transfer 4 KiB of data, which the
+//! SGB reads from the screen of the GameBoy.
+//!
+//! The SGB header flag must be set for the SGB to accept packets. Set it with
+//! the `sgb` key in `[package.metadata.rust-gb]` of `Cargo.toml`.
+//!
+//! ```toml
+//! [package.metadata.rust-gb]
+//! sgb = true
+//! ```
+//!
+//! On PAL SNES, the SGB misses packets sent in the first frames after
+//! startup. Wait about 4 frames before calling [`is_sgb`] or sending any
+//! packet.
+//!
+//! # Examples
+//! ```
+//! if sgb::is_sgb() {
+//!     sgb::set_palettes(
+//!         PalettePair::Pal01,
+//!         Rgb555::WHITE,
+//!         [Rgb555::new(31, 20, 0), Rgb555::new(20, 10, 0), Rgb555::BLACK],
+//!         [Rgb555::new(0, 20, 31), Rgb555::new(0, 10, 20), Rgb555::BLACK],
+//!     );
+//!     sgb::load_border(&BORDER_TILES, &BORDER_MAP, &BORDER_PALETTES);
+//! }
+//! ```
+//!
+//! Documents are provided by [GB Pan Docs](https://gbdev.io/pandocs/SGB_Functions.html)
+
+use core::ptr::addr_of_mut;
+
+use voladdress::{Unsafe, VolBlock};
+
+use crate::{
+    background::Background,
+    gbdk_c::gb::{
+        gb::delay,
+        sgb::{self, sgb_check, sgb_transfer},
+    },
+    lcd,
+    palette::{self, DmgPalette, Rgb555},
+    register::{self, Lcdc, TileDataArea, TileMapArea},
+    tiles::TileMap,
+};
+
+/// Size of a packet.
+pub const PACKET_SIZE: usize = 16;
+
+/// Maximum number of packets of a command.
+pub const MAX_PACKETS: u8 = 7;
+
+/// Maximum number of blocks of an ATTR_BLK command.
+pub const MAX_ATTR_BLOCKS: usize = 18;
+
+/// Maximum size of the border tiles, 256 tiles of 32 bytes (4bpp).
+pub const BORDER_TILES_SIZE: usize = 8192;
+
+/// Number of entries of the border map, 32x28 tiles.
+pub const BORDER_MAP_SIZE: usize = 32 * 28;
+
+/// Size of the data of a VRAM transfer.
+const TRANSFER_SIZE: usize = 4096;
+
+/// Offset of the border palettes in the PCT_TRN data.
+const BORDER_PALETTE_OFFSET: usize = 0x800;
+
+/// Time to wait after each packet, about 4 frames.
+const PACKET_DELAY_MS: u16 = 70;
+
+/// Result of [`is_sgb`], checked on the first call.
+static mut IS_SGB: Option<bool> = None;
+
+const TRANSFER_DATA: VolBlock<u8, Unsafe, Unsafe, TRANSFER_SIZE> = unsafe { VolBlock::new(0x8000) };
+
+/// Command of a packet.
+#[repr(u8)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Command {
+    /// Set SGB palettes 0 & 1.
+    Pal01 = sgb::SGB_PAL_01,
+    /// Set SGB palettes 2 & 3.
+    Pal23 = sgb::SGB_PAL_23,
+    /// Set SGB palettes 0 & 3.
+    Pal03 = sgb::SGB_PAL_03,
+    /// Set SGB palettes 1 & 2.
+    Pal12 = sgb::SGB_PAL_12,
+    /// Set color attributes for rectangular regions.
+    AttrBlk = sgb::SGB_ATTR_BLK,
+    /// Set color attributes for horizontal or vertical lines.
+    AttrLin = sgb::SGB_ATTR_LIN,
+    /// Split the screen in half, and set color attributes for each side.
+    AttrDiv = sgb::SGB_ATTR_DIV,
+    /// Set color attributes for separate characters.
+    AttrChr = sgb::SGB_ATTR_CHR,
+    /// Start and stop an internal sound effect.
+    Sound = sgb::SGB_SOUND,
+    /// Transfer sound code or data to the SNES APU RAM.
+    SouTrn = sgb::SGB_SOU_TRN,
+    /// Apply transferred system color palettes.
+    PalSet = sgb::SGB_PAL_SET,
+    /// Transfer system color palettes.
+    PalTrn = sgb::SGB_PAL_TRN,
+    /// Enable or disable the attraction mode.
+    AtrcEn = sgb::SGB_ATRC_EN,
+    /// Enable or disable the test mode.
+    TestEn = sgb::SGB_TEST_EN,
+    /// Enable or disable the icon functionality.
+    IconEn = sgb::SGB_ICON_EN,
+    /// Write bytes into SNES work RAM.
+    DataSnd = sgb::SGB_DATA_SND,
+    /// Transfer code or data into SNES RAM.
+    DataTrn = sgb::SGB_DATA_TRN,
+    /// Request multiplayer mode.
+    MltReq = sgb::SGB_MLT_REQ,
+    /// Set the SNES program counter.
+    Jump = sgb::SGB_JUMP,
+    /// Transfer border tiles.
+    ChrTrn = sgb::SGB_CHR_TRN,
+    /// Transfer the border map and palettes.
+    PctTrn = sgb::SGB_PCT_TRN,
+    /// Transfer attribute files.
+    AttrTrn = sgb::SGB_ATTR_TRN,
+    /// Apply an attribute file.
+    AttrSet = sgb::SGB_ATTR_SET,
+    /// Mask the GameBoy screen.
+    MaskEn = sgb::SGB_MASK_EN,
+    /// Transfer OBJ attributes to SNES OAM.
+    ObjTrn = sgb::SGB_OBJ_TRN,
+}
+
+/// A command packet.
+///
+/// The first byte is the command and the number of packets of the command,
+/// and the rest are the parameters.
+///
+/// # Examples
+/// ```
+/// // Disable the attraction mode.
+/// const ATRC_EN: Packet = Packet::new(Command::AtrcEn, 1).with_data(1, 1);
+/// sgb::send(&[ATRC_EN]);
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Packet(pub [u8; PACKET_SIZE]);
+
+impl Packet {
+    /// Creates the first packet of a command of `length` packets.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `length` is not from 1 to 7.
+    pub const fn new(command: Command, length: u8) -> Self {
+        if length == 0 || length > MAX_PACKETS {
+            panic!("SGB packet length outbounded");
+        }
+        let mut data = [0; PACKET_SIZE];
+        data[0] = ((command as u8) << 3) | length;
+        Packet(data)
+    }
+
+    /// Set a byte of the packet.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is greater than 15.
+    pub const fn with_data(self, index: usize, value: u8) -> Self {
+        let mut data = self.0;
+        data[index] = value;
+        Packet(data)
+    }
+}
+
+/// Whether the ROM is running on a Super GameBoy.
+///
+/// It requests the multiplayer mode and checks if the joypad ID changes, so
+/// it needs the SGB header flag. It is only checked on the first call, and
+/// the result is kept.
+pub fn is_sgb() -> bool {
+    // SAFETY: Only accessed from the main thread.
+    let detected = unsafe { &mut *addr_of_mut!(IS_SGB) };
+    // SAFETY: `sgb_check` only sends MLT_REQ packets and reads the joypad, and
+    // leaves the multiplayer mode before it returns.
+    *detected.get_or_insert_with(|| unsafe { sgb_check() != 0 })
+}
+
+/// Sends a packet, and waits for the SGB to process it. Does nothing on other
+/// hardware.
+fn transfer(packet: &[u8]) {
+    if !is_sgb() {
+        return;
+    }
+    // SAFETY: `packet` is always `PACKET_SIZE` bytes.
+    unsafe { sgb_transfer(packet.as_ptr()) };
+    // SAFETY: `delay` only waits, and doesn't touch any hardware state.
+    unsafe { delay(PACKET_DELAY_MS) };
+}
+
+/// Send packets to the SGB.
+///
+/// Every packet is followed by a delay of about 4 frames, for the SGB to
+/// process it. Does nothing on other hardware.
+pub fn send(packets: &[Packet]) {
+    for packet in packets {
+        transfer(&packet.0);
+    }
+}
+
+/// Pair of SGB palettes set by a command.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PalettePair {
+    /// Palettes 0 & 1
+    Pal01,
+    /// Palettes 2 & 3
+    Pal23,
+    /// Palettes 0 & 3
+    Pal03,
+    /// Palettes 1 & 2
+    Pal12,
+}
+
+/// Set two of the four SGB palettes.
+///
+/// Color 0 is shared by every palette, and `first` and `second` are colors 1
+/// to 3 of each palette.
+pub fn set_palettes(pair: PalettePair, color0: Rgb555, first: [Rgb555; 3], second: [Rgb555; 3]) {
+    let command = match pair {
+        PalettePair::Pal01 => Command::Pal01,
+        PalettePair::Pal23 => Command::Pal23,
+        PalettePair::Pal03 => Command::Pal03,
+        PalettePair::Pal12 => Command::Pal12,
+    };
+    let mut packet = Packet::new(command, 1);
+    let colors = [color0].into_iter().chain(first).chain(second);
+    for (i, color) in colors.enumerate() {
+        let [low, high] = color.bits().to_le_bytes();
+        packet.0[1 + i * 2] = low;
+        packet.0[2 + i * 2] = high;
+    }
+    send(&[packet]);
+}
+
+/// Rectangular region of the screen for ATTR_BLK, in tiles.
+///
+/// Each part of the region (inside, border and outside) is only changed if a
+/// palette is set for it.
+///
+/// # Examples
+/// ```
+/// // Palette 1 for the status bar, and palette 0 for the rest.
+/// const STATUS: AttrBlock = AttrBlock::new(0, 16, 19, 17).with_inside(1).with_outside(0);
+/// sgb::set_attr_blocks(&[STATUS]);
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct AttrBlock {
+    control: u8,
+    palettes: u8,
+    area: [u8; 4],
+}
+
+impl AttrBlock {
+    /// Creates a region from `(x1, y1)` to `(x2, y2)`, inclusive.
+    pub const fn new(x1: u8, y1: u8, x2: u8, y2: u8) -> Self {
+        AttrBlock {
+            control: 0,
+            palettes: 0,
+            area: [x1, y1, x2, y2],
+        }
+    }
+
+    const fn with_part(self, part: u8, palette: u8) -> Self {
+        AttrBlock {
+            control: self.control | (1 << part),
+            palettes: (self.palettes & !(0b11 << (part * 2))) | ((palette & 0b11) << (part * 2)),
+            area: self.area,
+        }
+    }
+
+    /// Set the palette inside the region.
+    pub const fn with_inside(self, palette: u8) -> Self {
+        self.with_part(0, palette)
+    }
+
+    /// Set the palette on the border of the region.
+    pub const fn with_border(self, palette: u8) -> Self {
+        self.with_part(1, palette)
+    }
+
+    /// Set the palette outside the region.
+    pub const fn with_outside(self, palette: u8) -> Self {
+        self.with_part(2, palette)
+    }
+
+    const fn to_bytes(self) -> [u8; 6] {
+        [
+            self.control,
+            self.palettes,
+            self.area[0],
+            self.area[1],
+            self.area[2],
+            self.area[3],
+        ]
+    }
+}
+
+/// Set the palettes of rectangular regions of the screen. (ATTR_BLK)
+///
+/// # Panics
+///
+/// Panics if `blocks` is empty or has more than 18 blocks.
+pub fn set_attr_blocks(blocks: &[AttrBlock]) {
+    if blocks.is_empty() || blocks.len() > MAX_ATTR_BLOCKS {
+        panic!("SGB attribute blocks outbounded");
+    }
+    let length = (2 + blocks.len() * 6).div_ceil(PACKET_SIZE);
+    let mut data = [0; PACKET_SIZE * MAX_PACKETS as usize];
+    data[0] = ((Command::AttrBlk as u8) << 3) | length as u8;
+    data[1] = blocks.len() as u8;
+    for (i, block) in blocks.iter().enumerate() {
+        data[2 + i * 6..8 + i * 6].copy_from_slice(&block.to_bytes());
+    }
+    for packet in data[..length * PACKET_SIZE].chunks_exact(PACKET_SIZE) {
+        transfer(packet);
+    }
+}
+
+/// Mask of the GameBoy screen on the SGB.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MaskMode {
+    /// Shows the screen.
+    Off = 0,
+    /// Freezes the current picture.
+    Freeze = 1,
+    /// Shows black.
+    Black = 2,
+    /// Shows color 0.
+    Color0 = 3,
+}
+
+/// Mask the GameBoy screen, for example while loading graphics. (MASK_EN)
+pub fn set_mask(mode: MaskMode) {
+    send(&[Packet::new(Command::MaskEn, 1).with_data(1, mode as u8)]);
+}
+
+/// Writes a byte of the transfer data.
+///
+/// Must be called while the LCD is off.
+fn write_transfer(offset: usize, value: u8) {
+    // SAFETY: VRAM is accessible while the LCD is off.
+    unsafe { TRANSFER_DATA.index(offset).write(value) };
+}
+
+/// Sends a command with 4 KiB of data written by `fill`.
+///
+/// The data is shown on the screen as tiles `0..256` of `$8000`, for the SGB
+/// to read it. VRAM at `$8000-$8FFF` and the tile map at `$9800` are
+/// overwritten, and LCDC, BGP and the scroll are restored.
+fn vram_transfer(packet: Packet, fill: impl FnOnce()) {
+    let lcdc = register::LCDC.read();
+    let bgp = palette::bg_palette();
+    let (scroll_x, scroll_y) = Background::position();
+    let map = TileMap::new(TileMapArea::Area9800);
+
+    lcd::with_lcd_off(|| {
+        for i in 0..TRANSFER_SIZE {
+            write_transfer(i, 0);
+        }
+        fill();
+        for tile in 0..=255u8 {
+            map.set_tile(tile % 20, tile / 20, tile);
+        }
+        // SAFETY: The LCD is off, and kept off.
+        unsafe {
+            register::LCDC.write(
+                Lcdc::new()
+                    .with_bg_enabled(true)
+                    .with_tile_data(TileDataArea::Area8000)
+                    .with_bg_map(TileMapArea::Area9800),
+            )
+        };
+    });
+    palette::set_bg_palette(DmgPalette::DEFAULT);
+    Background::scroll(0, 0);
+    // SAFETY: Turning on the LCD is safe at any time.
+    unsafe { register::LCDC.write(register::LCDC.read().with_lcd_enabled(true)) };
+
+    send(&[packet]);
+
+    lcd::wait_vblank();
+    // SAFETY: The LCD is in VBlank, so it can be turned off.
+    unsafe { register::LCDC.write(lcdc) };
+    Background::scroll(scroll_x, scroll_y);
+    palette::set_bg_palette(bgp);
+}
+
+/// Load a border, shown around the GameBoy screen on the SGB.
+///
+/// * `tiles` is up to 256 tiles in SNES 4bpp format, 32 bytes each.
+/// * Each entry of `map` has the tile in bits 0-7, the palette (4 to 7) in
+///   bits 10-12, the X flip in bit 14 and the Y flip in bit 15. The GameBoy
+///   screen covers the 20x18 tiles from (6, 5).
+/// * `palettes` are the SGB palettes 4 to 7, and color 0 is transparent.
+///
+/// The GameBoy screen is frozen during the transfer, which uses the screen,
+/// tiles `0..256` of `$8000` and the tile map at `$9800`. Load the graphics
+/// of the game after the border. Does nothing on other hardware.
+///
+/// # Panics
+///
+/// Panics if `tiles` is larger than 8192 bytes.
+pub fn load_border(tiles: &[u8], map: &[u16; BORDER_MAP_SIZE], palettes: &[[Rgb555; 16]; 4]) {
+    if tiles.len() > BORDER_TILES_SIZE {
+        panic!("SGB border tiles outbounded");
+    }
+    if !is_sgb() {
+        return;
+    }
+
+    set_mask(MaskMode::Freeze);
+    for (half, chunk) in tiles.chunks(TRANSFER_SIZE).enumerate() {
+        vram_transfer(
+            Packet::new(Command::ChrTrn, 1).with_data(1, half as u8),
+            || {
+                for (i, byte) in chunk.iter().enumerate() {
+                    write_transfer(i, *byte);
+                }
+            },
+        );
+    }
+    vram_transfer(Packet::new(Command::PctTrn, 1), || {
+        for (i, entry) in map.iter().enumerate() {
+            let [low, high] = entry.to_le_bytes();
+            write_transfer(i * 2, low);
+            write_transfer(i * 2 + 1, high);
+        }
+        for (i, color) in palettes.iter().flatten().enumerate() {
+            let [low, high] = color.bits().to_le_bytes();
+            write_transfer(BORDER_PALETTE_OFFSET + i * 2, low);
+            write_transfer(BORDER_PALETTE_OFFSET + i * 2 + 1, high);
+        }
+    });
+    set_mask(MaskMode::Off);
+}
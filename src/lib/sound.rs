@@ -0,0 +1,699 @@
+//! Audio processing unit (APU) of the GameBoy.
+//!
+//! The GameBoy has four sound channels. Two pulse channels ([`Pulse1`] with
+//! a period sweep, and [`Pulse2`]), a [`Wave`] channel which plays an
+//! arbitrary 4-bit waveform, and a [`Noise`] channel.
+//!
+//! Every channel is accessed through a handle of [`Apu`], and plays a "tone"
+//! ([`PulseTone`], [`WaveTone`], [`NoiseTone`]) that describes its envelope,
+//! length, duty and frequency. Tones are built with `const fn` builders, so
+//! they can be stored in ROM.
+//!
+//! Documents are provided by [GB Pan Docs](https://gbdev.io/pandocs/Audio.html)
+//!
+//! # Examples
+//! ```
+//! use gb::sound::{Apu, Envelope, EnvelopeDirection, Note, Pitch, PulseTone};
+//!
+//! const BEEP: PulseTone = PulseTone::new()
+//!     .with_note(Note::new(Pitch::A, 5))
+//!     .with_envelope(Envelope::new().with_volume(15).with_pace(2))
+//!     .with_length(32);
+//!
+//! let mut apu = Apu::enable();
+//! apu.pulse1.play(&BEEP);
+//! ```
+
+use voladdress::{Safe, Unsafe, VolAddress};
+
+use crate::{
+    mmio,
+    register::{self, AudioControl, LengthDuty, PeriodControl, WaveDac, WaveOutputLevel},
+};
+
+pub use crate::register::{
+    DutyCycle, Envelope, EnvelopeDirection, LfsrWidth, MasterVolume, NoiseControl, SoundPanning,
+    Sweep, SweepDirection, WaveVolume,
+};
+
+/// Maximum value of an 11-bit channel period.
+pub const MAX_PERIOD: u16 = 0x7FF;
+
+/// Pitch class of a [`Note`].
+#[repr(u8)]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Pitch {
+    C = 0,
+    CSharp = 1,
+    D = 2,
+    DSharp = 3,
+    E = 4,
+    F = 5,
+    FSharp = 6,
+    G = 7,
+    GSharp = 8,
+    A = 9,
+    ASharp = 10,
+    B = 11,
+}
+
+/// Pulse channel periods of each note from C2 to B8, in equal temperament
+/// with A4 = 440 Hz.
+///
+/// The period is calculated as `2048 - 131072 / frequency`.
+/// C2 (~65 Hz) is the lowest note that the pulse channels can play.
+pub const NOTE_PERIODS: [u16; 84] = [
+    44, 157, 263, 363, 457, 547, 631, 711, 786, 856, 923, 986, // Octave 2
+    1046, 1102, 1155, 1205, 1253, 1297, 1339, 1379, 1417, 1452, 1486, 1517, // Octave 3
+    1547, 1575, 1602, 1627, 1650, 1673, 1694, 1714, 1732, 1750, 1767, 1783, // Octave 4
+    1798, 1812, 1825, 1837, 1849, 1860, 1871, 1881, 1890, 1899, 1907, 1915, // Octave 5
+    1923, 1930, 1936, 1943, 1949, 1954, 1959, 1964, 1969, 1974, 1978, 1982, // Octave 6
+    1985, 1989, 1992, 1995, 1998, 2001, 2004, 2006, 2009, 2011, 2013, 2015, // Octave 7
+    2017, 2018, 2020, 2022, 2023, 2025, 2026, 2027, 2028, 2029, 2030, 2031, // Octave 8
+];
+
+/// A musical note from C2 to B8.
+///
+/// Internally, it is the number of semitones from C2, which is the index of
+/// [`NOTE_PERIODS`].
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Note(u8);
+
+impl Note {
+    /// The lowest note, C2.
+    pub const LOWEST: Note = Note(0);
+
+    /// The highest note, B8.
+    pub const HIGHEST: Note = Note(NOTE_PERIODS.len() as u8 - 1);
+
+    /// Creates a note from the pitch and the octave.
+    ///
+    /// # Panics
+    ///
+    /// Panics if octave is not in `2..=8`.
+    pub const fn new(pitch: Pitch, octave: u8) -> Self {
+        if octave < 2 || octave > 8 {
+            panic!("Note octave outbounded");
+        }
+        Note((octave - 2) * 12 + pitch as u8)
+    }
+
+    /// Creates a note from the number of semitones from C2.
+    ///
+    /// # Panics
+    ///
+    /// Panics if index is larger than [`Note::HIGHEST`].
+    pub const fn from_index(index: u8) -> Self {
+        if index as usize >= NOTE_PERIODS.len() {
+            panic!("Note index outbounded");
+        }
+        Note(index)
+    }
+
+    /// Number of semitones from C2.
+    pub const fn index(self) -> u8 {
+        self.0
+    }
+
+    /// Transposes the note by given semitones, returning `None` if the result
+    /// is out of range.
+    pub const fn checked_transpose(self, semitones: i8) -> Option<Self> {
+        let index = self.0 as i16 + semitones as i16;
+        if index < 0 || index >= NOTE_PERIODS.len() as i16 {
+            None
+        } else {
+            Some(Note(index as u8))
+        }
+    }
+
+    /// Period of the note for the pulse channels.
+    pub const fn period(self) -> u16 {
+        NOTE_PERIODS[self.0 as usize]
+    }
+
+    /// Period of the note for the wave channel.
+    ///
+    /// The wave channel plays one octave lower than the pulse channels with
+    /// the same period, so this is calculated as `2048 - 65536 / frequency`.
+    pub const fn wave_period(self) -> u16 {
+        (2048 + self.period()) / 2
+    }
+}
+
+/// Tone of the pulse channels ([`Pulse1`], [`Pulse2`]).
+///
+/// # Examples
+/// ```
+/// const JUMP: PulseTone = PulseTone::new()
+///     .with_duty(DutyCycle::Quarter)
+///     .with_period(1750)
+///     .with_envelope(Envelope::new().with_volume(12).with_pace(1));
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct PulseTone {
+    pub duty: DutyCycle,
+    pub envelope: Envelope,
+    /// Length in 256 Hz ticks, from `1` to `64`. `None` plays forever.
+    pub length: Option<u8>,
+    pub period: u16,
+}
+
+impl Default for PulseTone {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PulseTone {
+    /// Creates a silent tone, with a half duty cycle and no length.
+    pub const fn new() -> Self {
+        PulseTone {
+            duty: DutyCycle::Half,
+            envelope: Envelope::new(),
+            length: None,
+            period: 0,
+        }
+    }
+
+    /// Set duty cycle of `PulseTone`.
+    pub const fn with_duty(mut self, duty: DutyCycle) -> Self {
+        self.duty = duty;
+        self
+    }
+
+    /// Set volume envelope of `PulseTone`.
+    pub const fn with_envelope(mut self, envelope: Envelope) -> Self {
+        self.envelope = envelope;
+        self
+    }
+
+    /// Set length of `PulseTone`, in 256 Hz ticks.
+    ///
+    /// # Panics
+    ///
+    /// Panics if length is not in `1..=64`.
+    pub const fn with_length(mut self, length: u8) -> Self {
+        if length == 0 || length > 64 {
+            panic!("PulseTone length outbounded");
+        }
+        self.length = Some(length);
+        self
+    }
+
+    /// Set period of `PulseTone`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if period is larger than [`MAX_PERIOD`].
+    pub const fn with_period(mut self, period: u16) -> Self {
+        if period > MAX_PERIOD {
+            panic!("PulseTone period outbounded");
+        }
+        self.period = period;
+        self
+    }
+
+    /// Set period of `PulseTone` from a [`Note`].
+    pub const fn with_note(mut self, note: Note) -> Self {
+        self.period = note.period();
+        self
+    }
+}
+
+/// Tone of the [`Wave`] channel.
+///
+/// The waveform itself is loaded separately with [`Wave::load`].
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct WaveTone {
+    pub volume: WaveVolume,
+    /// Length in 256 Hz ticks, from `1` to `256`. `None` plays forever.
+    pub length: Option<u16>,
+    pub period: u16,
+}
+
+impl Default for WaveTone {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WaveTone {
+    /// Creates a tone with full volume and no length.
+    pub const fn new() -> Self {
+        WaveTone {
+            volume: WaveVolume::Full,
+            length: None,
+            period: 0,
+        }
+    }
+
+    /// Set output level of `WaveTone`.
+    pub const fn with_volume(mut self, volume: WaveVolume) -> Self {
+        self.volume = volume;
+        self
+    }
+
+    /// Set length of `WaveTone`, in 256 Hz ticks.
+    ///
+    /// # Panics
+    ///
+    /// Panics if length is not in `1..=256`.
+    pub const fn with_length(mut self, length: u16) -> Self {
+        if length == 0 || length > 256 {
+            panic!("WaveTone length outbounded");
+        }
+        self.length = Some(length);
+        self
+    }
+
+    /// Set period of `WaveTone`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if period is larger than [`MAX_PERIOD`].
+    pub const fn with_period(mut self, period: u16) -> Self {
+        if period > MAX_PERIOD {
+            panic!("WaveTone period outbounded");
+        }
+        self.period = period;
+        self
+    }
+
+    /// Set period of `WaveTone` from a [`Note`].
+    pub const fn with_note(mut self, note: Note) -> Self {
+        self.period = note.wave_period();
+        self
+    }
+}
+
+/// Tone of the [`Noise`] channel.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct NoiseTone {
+    pub envelope: Envelope,
+    pub noise: NoiseControl,
+    /// Length in 256 Hz ticks, from `1` to `64`. `None` plays forever.
+    pub length: Option<u8>,
+}
+
+impl Default for NoiseTone {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NoiseTone {
+    /// Creates a silent tone with no length.
+    pub const fn new() -> Self {
+        NoiseTone {
+            envelope: Envelope::new(),
+            noise: NoiseControl::new(),
+            length: None,
+        }
+    }
+
+    /// Set volume envelope of `NoiseTone`.
+    pub const fn with_envelope(mut self, envelope: Envelope) -> Self {
+        self.envelope = envelope;
+        self
+    }
+
+    /// Set frequency and randomness of `NoiseTone`.
+    pub const fn with_noise(mut self, noise: NoiseControl) -> Self {
+        self.noise = noise;
+        self
+    }
+
+    /// Set length of `NoiseTone`, in 256 Hz ticks.
+    ///
+    /// # Panics
+    ///
+    /// Panics if length is not in `1..=64`.
+    pub const fn with_length(mut self, length: u8) -> Self {
+        if length == 0 || length > 64 {
+            panic!("NoiseTone length outbounded");
+        }
+        self.length = Some(length);
+        self
+    }
+}
+
+/// Waveform of the [`Wave`] channel.
+///
+/// 32 samples of 4 bits, packed in the same layout as [`mmio::WAVE_RAM`]
+/// (upper nibble first).
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Waveform(pub [u8; 16]);
+
+impl Waveform {
+    /// Square wave.
+    pub const SQUARE: Waveform = Waveform([
+        0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00,
+    ]);
+
+    /// Triangle wave.
+    pub const TRIANGLE: Waveform = Waveform([
+        0x01, 0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD, 0xEF, 0xFE, 0xDC, 0xBA, 0x98, 0x76, 0x54, 0x32,
+        0x10,
+    ]);
+
+    /// Sawtooth wave.
+    pub const SAWTOOTH: Waveform = Waveform([
+        0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xAA, 0xBB, 0xCC, 0xDD, 0xEE,
+        0xFF,
+    ]);
+
+    /// Creates a waveform from 32 unpacked samples.
+    ///
+    /// Only lower 4 bits of each sample are used.
+    pub const fn from_samples(samples: [u8; 32]) -> Self {
+        let mut wave = [0; 16];
+        let mut i = 0;
+        while i < 16 {
+            wave[i] = (samples[i * 2] << 4) | (samples[i * 2 + 1] & 0x0F);
+            i += 1;
+        }
+        Waveform(wave)
+    }
+}
+
+/// Audio processing unit of the GameBoy.
+///
+/// Owns the handles of the four sound channels.
+///
+/// # Examples
+/// ```
+/// let mut apu = Apu::enable();
+/// apu.set_master_volume(MasterVolume::new().with_left(7).with_right(7));
+/// apu.set_panning(SoundPanning::CENTER);
+/// ```
+pub struct Apu {
+    pub pulse1: Pulse1,
+    pub pulse2: Pulse2,
+    pub wave: Wave,
+    pub noise: Noise,
+}
+
+impl Apu {
+    /// Turns on the APU.
+    ///
+    /// Master volume is set to maximum and every channel is panned to both
+    /// outputs.
+    pub fn enable() -> Self {
+        register::NR52.write(AudioControl::new().with_enabled(true));
+        register::NR50.write(MasterVolume::new().with_left(7).with_right(7));
+        register::NR51.write(SoundPanning::CENTER);
+        Apu {
+            pulse1: Pulse1 { length: 0 },
+            pulse2: Pulse2 { length: 0 },
+            wave: Wave { private: () },
+            noise: Noise { private: () },
+        }
+    }
+
+    /// Turns off the APU.
+    ///
+    /// This clears all sound registers and saves power.
+    pub fn disable(self) {
+        register::NR52.write(AudioControl::new());
+    }
+
+    /// Set master volume.
+    pub fn set_master_volume(&mut self, volume: MasterVolume) {
+        register::NR50.write(volume);
+    }
+
+    /// Set which channels are output to the left and right.
+    pub fn set_panning(&mut self, panning: SoundPanning) {
+        register::NR51.write(panning);
+    }
+
+    /// Get which channels are currently active.
+    pub fn status(&self) -> AudioControl {
+        register::NR52.read()
+    }
+}
+
+/// Registers of one pulse channel.
+struct PulseRegisters {
+    length_duty: VolAddress<LengthDuty, Unsafe, Safe>,
+    envelope: VolAddress<Envelope, Safe, Safe>,
+    period_low: VolAddress<u8, (), Safe>,
+    control: VolAddress<PeriodControl, Unsafe, Safe>,
+}
+
+const PULSE1: PulseRegisters = PulseRegisters {
+    length_duty: register::NR11,
+    envelope: register::NR12,
+    period_low: mmio::NR13,
+    control: register::NR14,
+};
+
+const PULSE2: PulseRegisters = PulseRegisters {
+    length_duty: register::NR21,
+    envelope: register::NR22,
+    period_low: mmio::NR23,
+    control: register::NR24,
+};
+
+impl PulseRegisters {
+    /// Triggers the channel, and returns the written length timer bits.
+    fn play(&self, tone: &PulseTone) -> u8 {
+        let length = match tone.length {
+            Some(length) => 64 - length,
+            None => 0,
+        };
+        self.length_duty
+            .write(LengthDuty::new().with_duty(tone.duty).with_length(length));
+        self.envelope.write(tone.envelope);
+        self.period_low.write(tone.period as u8);
+        self.control.write(
+            PeriodControl::new()
+                .with_trigger(true)
+                .with_length_enabled(tone.length.is_some())
+                .with_period_high((tone.period >> 8) as u8),
+        );
+        length
+    }
+
+    fn set_period(&self, period: u16) {
+        set_period(self.period_low, self.control, period);
+    }
+
+    fn set_duty(&self, duty: DutyCycle, length: u8) {
+        self.length_duty
+            .write(LengthDuty::new().with_duty(duty).with_length(length));
+    }
+
+    fn stop(&self) {
+        // Turning off the DAC turns off the channel.
+        self.envelope.write(Envelope::new());
+    }
+}
+
+/// Changes the period of a channel without retriggering it.
+fn set_period(
+    period_low: VolAddress<u8, (), Safe>,
+    control: VolAddress<PeriodControl, Unsafe, Safe>,
+    period: u16,
+) {
+    if period > MAX_PERIOD {
+        panic!("Sound period outbounded");
+    }
+    // SAFETY: Only the length enable bit is readable, and it is the only bit
+    // used from the read value.
+    let length_enabled = unsafe { control.read() }.length_enabled();
+    period_low.write(period as u8);
+    control.write(
+        PeriodControl::new()
+            .with_length_enabled(length_enabled)
+            .with_period_high((period >> 8) as u8),
+    );
+}
+
+/// Sound channel 1, pulse with period sweep.
+pub struct Pulse1 {
+    /// Length timer bits of the last tone, written back with the duty.
+    length: u8,
+}
+
+impl Pulse1 {
+    /// Set period sweep of the channel.
+    ///
+    /// The sweep is applied from the next [`Pulse1::play`].
+    pub fn set_sweep(&mut self, sweep: Sweep) {
+        register::NR10.write(sweep);
+    }
+
+    /// Triggers the channel with given tone.
+    pub fn play(&mut self, tone: &PulseTone) {
+        self.length = PULSE1.play(tone);
+    }
+
+    /// Changes the period (pitch) without retriggering the channel.
+    ///
+    /// # Panics
+    ///
+    /// Panics if period is larger than [`MAX_PERIOD`].
+    pub fn set_period(&mut self, period: u16) {
+        PULSE1.set_period(period);
+    }
+
+    /// Changes the duty cycle without retriggering the channel.
+    ///
+    /// The length timer bits share the register with the duty, so the length
+    /// of the last tone is written again, which reloads the length timer.
+    pub fn set_duty(&mut self, duty: DutyCycle) {
+        PULSE1.set_duty(duty, self.length);
+    }
+
+    /// Stops the channel.
+    pub fn stop(&mut self) {
+        PULSE1.stop();
+    }
+
+    /// Whether the channel is currently playing.
+    pub fn is_active(&self) -> bool {
+        register::NR52.read().ch1_on()
+    }
+}
+
+/// Sound channel 2, pulse.
+pub struct Pulse2 {
+    /// Length timer bits of the last tone, written back with the duty.
+    length: u8,
+}
+
+impl Pulse2 {
+    /// Triggers the channel with given tone.
+    pub fn play(&mut self, tone: &PulseTone) {
+        self.length = PULSE2.play(tone);
+    }
+
+    /// Changes the period (pitch) without retriggering the channel.
+    ///
+    /// # Panics
+    ///
+    /// Panics if period is larger than [`MAX_PERIOD`].
+    pub fn set_period(&mut self, period: u16) {
+        PULSE2.set_period(period);
+    }
+
+    /// Changes the duty cycle without retriggering the channel.
+    ///
+    /// The length timer bits share the register with the duty, so the length
+    /// of the last tone is written again, which reloads the length timer.
+    pub fn set_duty(&mut self, duty: DutyCycle) {
+        PULSE2.set_duty(duty, self.length);
+    }
+
+    /// Stops the channel.
+    pub fn stop(&mut self) {
+        PULSE2.stop();
+    }
+
+    /// Whether the channel is currently playing.
+    pub fn is_active(&self) -> bool {
+        register::NR52.read().ch2_on()
+    }
+}
+
+/// Sound channel 3, wave output.
+pub struct Wave {
+    private: (),
+}
+
+impl Wave {
+    /// Loads a waveform into [`mmio::WAVE_RAM`].
+    ///
+    /// Accessing wave RAM while the channel is active misbehaves, so the
+    /// channel is stopped by turning off its DAC before loading. The channel
+    /// stays silent until the next [`Wave::play`].
+    pub fn load(&mut self, waveform: &Waveform) {
+        register::NR30.write(WaveDac::new());
+        for (i, sample) in waveform.0.iter().enumerate() {
+            // SAFETY: CH3 is not active since its DAC is off.
+            unsafe { mmio::WAVE_RAM.index(i).write(*sample) };
+        }
+    }
+
+    /// Triggers the channel with given tone.
+    pub fn play(&mut self, tone: &WaveTone) {
+        register::NR30.write(WaveDac::new().with_enabled(true));
+        let length = match tone.length {
+            Some(length) => (256 - length) as u8,
+            None => 0,
+        };
+        mmio::NR31.write(length);
+        register::NR32.write(WaveOutputLevel::new().with_volume(tone.volume));
+        mmio::NR33.write(tone.period as u8);
+        register::NR34.write(
+            PeriodControl::new()
+                .with_trigger(true)
+                .with_length_enabled(tone.length.is_some())
+                .with_period_high((tone.period >> 8) as u8),
+        );
+    }
+
+    /// Changes the period (pitch) without retriggering the channel.
+    ///
+    /// # Panics
+    ///
+    /// Panics if period is larger than [`MAX_PERIOD`].
+    pub fn set_period(&mut self, period: u16) {
+        set_period(mmio::NR33, register::NR34, period);
+    }
+
+    /// Changes the output level without retriggering the channel.
+    pub fn set_volume(&mut self, volume: WaveVolume) {
+        register::NR32.write(WaveOutputLevel::new().with_volume(volume));
+    }
+
+    /// Stops the channel.
+    pub fn stop(&mut self) {
+        register::NR30.write(WaveDac::new());
+    }
+
+    /// Whether the channel is currently playing.
+    pub fn is_active(&self) -> bool {
+        register::NR52.read().ch3_on()
+    }
+}
+
+/// Sound channel 4, noise.
+pub struct Noise {
+    private: (),
+}
+
+impl Noise {
+    /// Triggers the channel with given tone.
+    pub fn play(&mut self, tone: &NoiseTone) {
+        let length = match tone.length {
+            Some(length) => 64 - length,
+            None => 0,
+        };
+        mmio::NR41.write(length);
+        register::NR42.write(tone.envelope);
+        register::NR43.write(tone.noise);
+        register::NR44.write(
+            PeriodControl::new()
+                .with_trigger(true)
+                .with_length_enabled(tone.length.is_some()),
+        );
+    }
+
+    /// Changes the frequency and randomness without retriggering the channel.
+    pub fn set_noise(&mut self, noise: NoiseControl) {
+        register::NR43.write(noise);
+    }
+
+    /// Stops the channel.
+    pub fn stop(&mut self) {
+        register::NR42.write(Envelope::new());
+    }
+
+    /// Whether the channel is currently playing.
+    pub fn is_active(&self) -> bool {
+        register::NR52.read().ch4_on()
+    }
+}
@@ -0,0 +1,414 @@
+//! Hardware sprites (OBJ).
+//!
+//! The GameBoy can show 40 sprites, described in OAM (`$FE00-$FE9F`). OAM
+//! can't be written while the PPU reads it, so sprites are written into a
+//! shadow OAM in WRAM and copied into OAM by OAM DMA during VBlank.
+//!
+//! The shadow OAM is the 160 bytes at `$C000`, reserved and cleared by the
+//! GBDK startup code, which is aligned to 256 bytes as OAM DMA requires. The
+//! startup code also installs the DMA routine into HRAM (the CPU can only
+//! access HRAM during OAM DMA), and the VBlank interrupt handler runs it every
+//! frame. (See [`set_auto_transfer`] to turn it off)
+//!
+//! # Examples
+//! ```
+//! tiles::load_tiles(0, &PLAYER_TILES);
+//! sprite::show();
+//!
+//! let mut player = Sprite::new().unwrap();
+//! player.set_tile(0);
+//! player.set_position(80 + sprite::X_OFFSET, 72 + sprite::Y_OFFSET);
+//! player.set_attributes(ObjAttributes::new().with_x_flip(true));
+//! ```
+//!
+//! Documents are provided by [GB Pan Docs](https://gbdev.io/pandocs/OAM.html)
+
+use core::ptr::{addr_of, addr_of_mut};
+
+use voladdress::{Safe, VolBlock};
+
+use crate::{
+    gbdk_c::gb::gb::{_shadow_OAM_base, refresh_OAM, shadow_OAM, MAX_HARDWARE_SPRITES},
+    irq::critical_section,
+    lcd,
+    register::{self, bit_enum, enum_field, field, flag, register_type, ObjSize},
+};
+
+/// Number of hardware sprites.
+pub const SPRITE_COUNT: u8 = MAX_HARDWARE_SPRITES;
+
+/// Offset of the OAM X coordinate from the screen X coordinate.
+pub const X_OFFSET: u8 = 8;
+
+/// Offset of the OAM Y coordinate from the screen Y coordinate.
+pub const Y_OFFSET: u8 = 16;
+
+static mut ALLOCATED: [u8; 5] = [0; 5];
+
+/// Must be called in a critical section, as handlers may allocate sprites.
+fn is_allocated(index: u8) -> bool {
+    // SAFETY: Interrupts are disabled by the callers.
+    let allocated = unsafe { &*addr_of!(ALLOCATED) };
+    allocated[(index / 8) as usize] & (1 << (index % 8)) != 0
+}
+
+/// Must be called in a critical section, as handlers may allocate sprites.
+fn set_allocated(index: u8, value: bool) {
+    // SAFETY: Interrupts are disabled by the callers.
+    let allocated = unsafe { &mut *addr_of_mut!(ALLOCATED) };
+    if value {
+        allocated[(index / 8) as usize] |= 1 << (index % 8);
+    } else {
+        allocated[(index / 8) as usize] &= !(1 << (index % 8));
+    }
+}
+
+/// Allocates `count` consecutive hardware sprites, hidden, and returns the
+/// first index.
+pub(crate) fn allocate_range(count: u8) -> Option<u8> {
+    let last_start = SPRITE_COUNT.checked_sub(count)?;
+    let start = critical_section(|| {
+        let start = (0..=last_start)
+            .find(|&start| (start..start + count).all(|index| !is_allocated(index)))?;
+        for index in start..start + count {
+            set_allocated(index, true);
+        }
+        Some(start)
+    })?;
+    for index in start..start + count {
+        set_entry(index, OamEntry::HIDDEN);
+    }
+    Some(start)
+}
+
+/// Hides and frees hardware sprites allocated by [`allocate_range`].
+pub(crate) fn free_range(start: u8, count: u8) {
+    for index in start..start + count {
+        set_entry(index, OamEntry::HIDDEN);
+    }
+    critical_section(|| {
+        for index in start..start + count {
+            set_allocated(index, false);
+        }
+    });
+}
+
+bit_enum!(
+    /// DMG palette used by a sprite.
+    #[derive(Debug)]
+    ObjPalette {
+        /// OBP0
+        Obp0,
+        /// OBP1
+        Obp1,
+    }
+);
+
+register_type!(
+    /// Attributes (flags) of a sprite.
+    #[derive(Debug)]
+    ObjAttributes
+);
+
+impl ObjAttributes {
+    flag!(
+        /// Whether BG and window colors 1-3 are drawn over this sprite.
+        behind_bg, with_behind_bg, set_behind_bg, 7
+    );
+    flag!(
+        /// Whether the sprite is vertically mirrored.
+        y_flip, with_y_flip, set_y_flip, 6
+    );
+    flag!(
+        /// Whether the sprite is horizontally mirrored.
+        x_flip, with_x_flip, set_x_flip, 5
+    );
+    enum_field!(
+        /// DMG palette of the sprite. (Non-CGB mode only)
+        palette, with_palette, set_palette, ObjPalette, 4, 0b1
+    );
+    field!(
+        /// VRAM bank of the tile. (CGB mode only)
+        vram_bank, with_vram_bank, set_vram_bank, 3, 0b1
+    );
+    field!(
+        /// CGB palette of the sprite, from `0` to `7`. (CGB mode only)
+        cgb_palette, with_cgb_palette, set_cgb_palette, 0, 0b111
+    );
+}
+
+/// One of 40 entries in OAM.
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct OamEntry {
+    /// Y position plus 16. The sprite is hidden if it is 0 or 160 and above.
+    pub y: u8,
+    /// X position plus 8. The sprite is hidden if it is 0 or 168 and above.
+    pub x: u8,
+    /// Tile index, from `$8000`.
+    pub tile: u8,
+    /// Attributes
+    pub attributes: ObjAttributes,
+}
+
+impl OamEntry {
+    /// A hidden entry.
+    pub const HIDDEN: OamEntry = OamEntry {
+        y: 0,
+        x: 0,
+        tile: 0,
+        attributes: ObjAttributes::new(),
+    };
+}
+
+fn shadow_oam() -> VolBlock<OamEntry, Safe, Safe, { SPRITE_COUNT as usize }> {
+    // SAFETY: The shadow OAM is 40 entries in WRAM reserved by the startup
+    // code, and `OamEntry` has the same layout as `OAM_item_t`.
+    unsafe { VolBlock::new(addr_of_mut!(shadow_OAM) as usize) }
+}
+
+/// Get an entry of the shadow OAM.
+///
+/// # Panics
+///
+/// Panics if `index` is 40 or more.
+pub fn entry(index: u8) -> OamEntry {
+    shadow_oam().index(index as usize).read()
+}
+
+/// Set an entry of the shadow OAM directly, regardless of the allocation.
+///
+/// # Panics
+///
+/// Panics if `index` is 40 or more.
+pub fn set_entry(index: u8, entry: OamEntry) {
+    shadow_oam().index(index as usize).write(entry);
+}
+
+/// Hide every sprite in the shadow OAM.
+///
+/// Allocated [`Sprite`]s stay allocated.
+pub fn hide_all() {
+    for entry in shadow_oam().iter() {
+        entry.write(OamEntry::HIDDEN);
+    }
+}
+
+/// Show sprites.
+pub fn show() {
+    lcd::update_lcdc(|lcdc| {
+        lcdc.set_obj_enabled(true);
+    });
+}
+
+/// Hide sprites.
+pub fn hide() {
+    lcd::update_lcdc(|lcdc| {
+        lcdc.set_obj_enabled(false);
+    });
+}
+
+/// Set the size of every sprite.
+///
+/// In 8x16 mode, the sprite uses tiles `tile & 0xFE` and `tile | 0x01`.
+pub fn set_size(size: ObjSize) {
+    lcd::update_lcdc(|lcdc| {
+        lcdc.set_obj_size(size);
+    });
+}
+
+/// Size of every sprite.
+pub fn size() -> ObjSize {
+    register::LCDC.read().obj_size()
+}
+
+fn shadow_oam_page() -> u8 {
+    (addr_of!(shadow_OAM) as usize >> 8) as u8
+}
+
+fn set_shadow_oam_base(base: u8) {
+    // SAFETY: `_shadow_OAM_base` is a byte in HRAM read by the VBlank handler.
+    unsafe { addr_of_mut!(_shadow_OAM_base).write_volatile(base) };
+}
+
+/// Set whether the shadow OAM is copied into OAM on every VBlank interrupt.
+///
+/// It is turned on by default. Turn it off to update many sprites across
+/// frames without showing a half-updated state, then call [`transfer`].
+pub fn set_auto_transfer(enabled: bool) {
+    set_shadow_oam_base(if enabled { shadow_oam_page() } else { 0 });
+}
+
+/// Whether the shadow OAM is copied into OAM on every VBlank interrupt.
+pub fn is_auto_transfer() -> bool {
+    // SAFETY: `_shadow_OAM_base` is a byte in HRAM.
+    unsafe { addr_of!(_shadow_OAM_base).read_volatile() != 0 }
+}
+
+/// Copies the shadow OAM into OAM now, with the DMA routine in HRAM.
+///
+/// Waits until VBlank first, because the PPU can't read OAM during OAM DMA.
+pub fn transfer() {
+    let auto_transfer = is_auto_transfer();
+    // The DMA routine copies from the page in `_shadow_OAM_base`.
+    set_shadow_oam_base(shadow_oam_page());
+    lcd::wait_vblank();
+    // SAFETY: The DMA routine is installed in HRAM by the startup code.
+    unsafe { refresh_OAM() };
+    set_auto_transfer(auto_transfer);
+}
+
+/// Allocated hardware sprite.
+///
+/// The slot is freed and the sprite is hidden when it is dropped.
+#[derive(PartialEq, Eq, Debug)]
+pub struct Sprite {
+    index: u8,
+}
+
+impl Sprite {
+    /// Allocates a free hardware sprite.
+    ///
+    /// The sprite is hidden until its position is set. Returns `None` if all
+    /// 40 sprites are in use.
+    pub fn new() -> Option<Self> {
+        (0..SPRITE_COUNT).find_map(Self::with_index)
+    }
+
+    /// Allocates the hardware sprite of given index.
+    ///
+    /// Sprites with lower index are drawn over the others on DMG. Returns
+    /// `None` if the sprite is already in use.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is 40 or more.
+    pub fn with_index(index: u8) -> Option<Self> {
+        if index >= SPRITE_COUNT {
+            panic!("Sprite index outbounded");
+        }
+        let free = critical_section(|| {
+            let free = !is_allocated(index);
+            if free {
+                set_allocated(index, true);
+            }
+            free
+        });
+        if !free {
+            return None;
+        }
+        set_entry(index, OamEntry::HIDDEN);
+        Some(Sprite { index })
+    }
+
+    /// Index of the sprite in OAM.
+    pub fn index(&self) -> u8 {
+        self.index
+    }
+
+    /// Entry of the sprite in the shadow OAM.
+    pub fn entry(&self) -> OamEntry {
+        entry(self.index)
+    }
+
+    /// Set the entry of the sprite in the shadow OAM.
+    pub fn set_entry(&mut self, entry: OamEntry) {
+        set_entry(self.index, entry);
+    }
+
+    fn update(&mut self, f: impl FnOnce(&mut OamEntry)) {
+        let mut entry = self.entry();
+        f(&mut entry);
+        self.set_entry(entry);
+    }
+
+    /// Move the sprite to the OAM coordinate.
+    ///
+    /// The screen coordinate is `(x - 8, y - 16)`. (See [`X_OFFSET`] and
+    /// [`Y_OFFSET`])
+    pub fn set_position(&mut self, x: u8, y: u8) {
+        self.update(|entry| {
+            entry.x = x;
+            entry.y = y;
+        });
+    }
+
+    /// Move the sprite relatively, wrapping around.
+    pub fn move_by(&mut self, dx: i8, dy: i8) {
+        self.update(|entry| {
+            entry.x = entry.x.wrapping_add_signed(dx);
+            entry.y = entry.y.wrapping_add_signed(dy);
+        });
+    }
+
+    /// OAM coordinate `(x, y)` of the sprite.
+    pub fn position(&self) -> (u8, u8) {
+        let entry = self.entry();
+        (entry.x, entry.y)
+    }
+
+    /// Hide the sprite by moving it out of the screen.
+    pub fn hide(&mut self) {
+        self.update(|entry| {
+            entry.y = 0;
+        });
+    }
+
+    /// Set the tile of the sprite.
+    pub fn set_tile(&mut self, tile: u8) {
+        self.update(|entry| {
+            entry.tile = tile;
+        });
+    }
+
+    /// Tile of the sprite.
+    pub fn tile(&self) -> u8 {
+        self.entry().tile
+    }
+
+    /// Set the attributes of the sprite.
+    pub fn set_attributes(&mut self, attributes: ObjAttributes) {
+        self.update(|entry| {
+            entry.attributes = attributes;
+        });
+    }
+
+    /// Attributes of the sprite.
+    pub fn attributes(&self) -> ObjAttributes {
+        self.entry().attributes
+    }
+
+    /// Set whether the sprite is horizontally mirrored.
+    pub fn set_x_flip(&mut self, value: bool) {
+        self.update(|entry| {
+            entry.attributes = entry.attributes.with_x_flip(value);
+        });
+    }
+
+    /// Set whether the sprite is vertically mirrored.
+    pub fn set_y_flip(&mut self, value: bool) {
+        self.update(|entry| {
+            entry.attributes = entry.attributes.with_y_flip(value);
+        });
+    }
+
+    /// Set whether BG and window colors 1-3 are drawn over the sprite.
+    pub fn set_behind_bg(&mut self, value: bool) {
+        self.update(|entry| {
+            entry.attributes = entry.attributes.with_behind_bg(value);
+        });
+    }
+
+    /// Set the DMG palette of the sprite.
+    pub fn set_palette(&mut self, palette: ObjPalette) {
+        self.update(|entry| {
+            entry.attributes = entry.attributes.with_palette(palette);
+        });
+    }
+}
+
+impl Drop for Sprite {
+    fn drop(&mut self) {
+        free_range(self.index, 1);
+    }
+}
@@ -0,0 +1,82 @@
+//! Hardware model detection.
+//!
+//! The boot ROM leaves the model of the hardware in the A and B registers
+//! when it jumps to the cartridge. The startup code of GBDK saves them
+//! before anything else runs, and [`model`] reads them back.
+//!
+//! | Model           | A      | B (bit 0) |
+//! |-----------------|--------|-----------|
+//! | DMG, SGB        | `0x01` | -         |
+//! | MGB, SGB2       | `0xFF` | -         |
+//! | CGB             | `0x11` | 0         |
+//! | GBA (AGB)       | `0x11` | 1         |
+//!
+//! The SGB can't be told apart from the DMG or MGB by the registers. (See
+//! `gb::sgb` for that.)
+//!
+//! # Examples
+//! ```
+//! if system::model().supports_color() {
+//!     palette::set_cgb_bg_palettes(0, &PALETTES);
+//! } else {
+//!     palette::set_bg_palette(DmgPalette::DEFAULT);
+//! }
+//! ```
+//!
+//! Documents are provided by [GB Pan Docs](https://gbdev.io/pandocs/Power_Up_Sequence.html#console-state-after-boot-rom-hand-off)
+
+use crate::gbdk_c::gb::gb::{_cpu, _is_GBA, CGB_TYPE, GBA_DETECTED, MGB_TYPE};
+
+/// CGB flag of the cartridge header, set by `cgb` in
+/// `[package.metadata.rust-gb]` of `Cargo.toml`.
+const CGB_FLAG_ADDR: usize = 0x0143;
+
+/// Bit of the CGB flag that makes the GameBoy Color run the ROM in CGB mode.
+const CGB_FLAG_CGB_MODE: u8 = 0x80;
+
+/// Hardware model of the GameBoy.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Model {
+    /// Original GameBoy, or Super GameBoy.
+    Dmg,
+    /// GameBoy Pocket or Light, or Super GameBoy 2.
+    Mgb,
+    /// GameBoy Color.
+    Cgb,
+    /// GameBoy Advance, running a GameBoy ROM.
+    Agb,
+}
+
+impl Model {
+    /// Whether the hardware has the features of the GameBoy Color.
+    ///
+    /// They are only available if the ROM is built in CGB mode. Otherwise,
+    /// the GameBoy Color runs it in DMG compatibility mode. (See [`is_cgb_mode`])
+    pub const fn supports_color(self) -> bool {
+        matches!(self, Model::Cgb | Model::Agb)
+    }
+}
+
+/// Hardware model the ROM is running on.
+pub fn model() -> Model {
+    // SAFETY: Both are written once by the startup code, before `main`.
+    let (cpu, is_gba) = unsafe { (_cpu, _is_GBA) };
+    match cpu {
+        CGB_TYPE if is_gba & GBA_DETECTED != 0 => Model::Agb,
+        CGB_TYPE => Model::Cgb,
+        MGB_TYPE => Model::Mgb,
+        _ => Model::Dmg,
+    }
+}
+
+/// Whether the ROM is running in CGB mode, with the features of the GameBoy
+/// Color unlocked.
+///
+/// It needs both the GameBoy Color hardware and a ROM built with `cgb` set to
+/// `"compatible"` or `"only"`. With `cgb = "none"`, the GameBoy Color runs
+/// the ROM in DMG compatibility mode, where the CGB registers are locked.
+pub fn is_cgb_mode() -> bool {
+    // SAFETY: The cartridge header is read-only ROM.
+    let flag = unsafe { core::ptr::read_volatile(CGB_FLAG_ADDR as *const u8) };
+    model().supports_color() && flag & CGB_FLAG_CGB_MODE != 0
+}
@@ -0,0 +1,379 @@
+//! Tile data and tile maps in VRAM.
+//!
+//! The GameBoy graphics are built with 8x8 pixel tiles. Tile data (the pixels
+//! of each tile) lives in `$8000-$97FF`, room for 384 tiles, and two 32x32
+//! tile maps (which tile is drawn where) live in `$9800-$9BFF` and
+//! `$9C00-$9FFF`.
+//!
+//! Every function in this module waits until VRAM is accessible before each
+//! access (see [`crate::lcd::wait_vram`]), so it is safe to call at any time.
+//! For a large transfer, it is much faster to call it inside
+//! [`crate::lcd::with_lcd_off`] or during VBlank.
+//!
+//! With the `color` feature, [`VramBank`] selects the second VRAM bank of the
+//! GameBoy Color, which has 384 more tiles and the [`AttributeMap`]s.
+//!
+//! Documents are provided by [GB Pan Docs](https://gbdev.io/pandocs/Tile_Data.html)
+
+use voladdress::{Unsafe, VolBlock};
+
+use crate::{lcd, register::TileMapArea};
+
+#[cfg(any(feature = "color", doc))]
+#[doc(cfg(feature = "color"))]
+pub use color::{AttributeMap, BgAttributes, VramBank};
+
+/// Number of tiles in the tile data area of VRAM.
+pub const TILE_COUNT: usize = 384;
+
+/// Width and height of a tile map, in tiles.
+pub const MAP_SIZE: u8 = 32;
+
+const TILE_DATA: VolBlock<u8, Unsafe, Unsafe, { TILE_COUNT * 16 }> =
+    unsafe { VolBlock::new(0x8000) };
+
+/// Writes a byte into VRAM, waiting until VRAM is accessible.
+fn write_vram<const C: usize>(block: VolBlock<u8, Unsafe, Unsafe, C>, index: usize, value: u8) {
+    lcd::wait_vram();
+    // SAFETY: VRAM is accessible.
+    unsafe { block.index(index).write(value) };
+}
+
+/// 8x8 pixel tile in the 2bpp format of the GameBoy.
+///
+/// Each row is two bytes. The first byte has the low bit of the color index of
+/// each pixel, and the second byte has the high bit, with the leftmost pixel
+/// in bit 7.
+///
+/// # Examples
+/// ```
+/// const BOX: Tile = Tile::from_indices([
+///     [3, 3, 3, 3, 3, 3, 3, 3],
+///     [3, 0, 0, 0, 0, 0, 0, 3],
+///     [3, 0, 1, 1, 1, 1, 0, 3],
+///     [3, 0, 1, 2, 2, 1, 0, 3],
+///     [3, 0, 1, 2, 2, 1, 0, 3],
+///     [3, 0, 1, 1, 1, 1, 0, 3],
+///     [3, 0, 0, 0, 0, 0, 0, 3],
+///     [3, 3, 3, 3, 3, 3, 3, 3],
+/// ]);
+/// ```
+#[repr(transparent)]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Tile(pub [u8; 16]);
+
+impl Tile {
+    /// A tile filled with color index 0.
+    pub const EMPTY: Tile = Tile([0; 16]);
+
+    /// Creates a tile from rows of color indices (`0` to `3`).
+    ///
+    /// Only lower 2 bits of each index are used.
+    pub const fn from_indices(pixels: [[u8; 8]; 8]) -> Self {
+        let mut data = [0; 16];
+        let mut y = 0;
+        while y < 8 {
+            let mut x = 0;
+            while x < 8 {
+                let index = pixels[y][x];
+                data[y * 2] |= (index & 0b01) << (7 - x);
+                data[y * 2 + 1] |= ((index & 0b10) >> 1) << (7 - x);
+                x += 1;
+            }
+            y += 1;
+        }
+        Tile(data)
+    }
+
+    /// Creates a tile filled with one color index.
+    pub const fn filled(index: u8) -> Self {
+        let low = if index & 0b01 != 0 { 0xFF } else { 0x00 };
+        let high = if index & 0b10 != 0 { 0xFF } else { 0x00 };
+        let mut data = [0; 16];
+        let mut i = 0;
+        while i < 8 {
+            data[i * 2] = low;
+            data[i * 2 + 1] = high;
+            i += 1;
+        }
+        Tile(data)
+    }
+
+    /// Color index of a pixel.
+    ///
+    /// # Panics
+    ///
+    /// Panics if coordinate parameter out of bounds.
+    pub const fn pixel(&self, x: u8, y: u8) -> u8 {
+        if x >= 8 || y >= 8 {
+            panic!("Tile pixel outbounded");
+        }
+        let low = (self.0[y as usize * 2] >> (7 - x)) & 1;
+        let high = (self.0[y as usize * 2 + 1] >> (7 - x)) & 1;
+        (high << 1) | low
+    }
+}
+
+/// Loads tiles into the tile data area of VRAM.
+///
+/// `start` is the index of the first tile from `$8000`, from `0` to `383`.
+/// Tiles `0..256` are used by OBJ and the BG in [`TileDataArea::Area8000`]
+/// mode, and tiles `128..384` are used by the BG in
+/// [`TileDataArea::Area8800`] mode.
+///
+/// [`TileDataArea::Area8000`]: crate::register::TileDataArea::Area8000
+/// [`TileDataArea::Area8800`]: crate::register::TileDataArea::Area8800
+///
+/// # Panics
+///
+/// Panics if the tiles do not fit in VRAM.
+pub fn load_tiles(start: usize, tiles: &[Tile]) {
+    if start + tiles.len() > TILE_COUNT {
+        panic!("Tile index outbounded");
+    }
+    for (i, tile) in tiles.iter().enumerate() {
+        let base = (start + i) * 16;
+        for (j, byte) in tile.0.iter().enumerate() {
+            write_vram(TILE_DATA, base + j, *byte);
+        }
+    }
+}
+
+/// One of the two 32x32 tile maps in VRAM.
+///
+/// The maps wrap around. Writing a rectangle that crosses the right or bottom
+/// edge continues from the left or top edge.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct TileMap {
+    area: TileMapArea,
+}
+
+impl TileMap {
+    /// Creates a handle of the tile map in given area.
+    pub const fn new(area: TileMapArea) -> Self {
+        TileMap { area }
+    }
+
+    /// Area of the tile map.
+    pub const fn area(&self) -> TileMapArea {
+        self.area
+    }
+
+    fn block(&self) -> VolBlock<u8, Unsafe, Unsafe, 0x400> {
+        // SAFETY: Both areas are 1KB of VRAM.
+        unsafe { VolBlock::new(self.area.address()) }
+    }
+
+    fn index(x: u8, y: u8) -> usize {
+        (y % MAP_SIZE) as usize * MAP_SIZE as usize + (x % MAP_SIZE) as usize
+    }
+
+    /// Set a tile at given coordinate.
+    pub fn set_tile(&self, x: u8, y: u8, tile: u8) {
+        write_vram(self.block(), Self::index(x, y), tile);
+    }
+
+    /// Get the tile at given coordinate.
+    pub fn tile(&self, x: u8, y: u8) -> u8 {
+        lcd::wait_vram();
+        // SAFETY: VRAM is accessible.
+        unsafe { self.block().index(Self::index(x, y)).read() }
+    }
+
+    /// Set a rectangle of tiles, given in row-major order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tiles` is shorter than `width * height`.
+    pub fn set_tiles(&self, x: u8, y: u8, width: u8, height: u8, tiles: &[u8]) {
+        if tiles.len() < width as usize * height as usize {
+            panic!("Tile map data too short");
+        }
+        for row in 0..height {
+            for column in 0..width {
+                let tile = tiles[row as usize * width as usize + column as usize];
+                self.set_tile(x.wrapping_add(column), y.wrapping_add(row), tile);
+            }
+        }
+    }
+
+    /// Write ASCII text in a row, starting from given coordinate.
+    ///
+    /// The font is expected to be loaded in ASCII order from the space
+    /// character, so that the tile of a character `c` is
+    /// `first_tile + (c - b' ')`. Characters other than printable ASCII are
+    /// written as a space.
+    ///
+    /// # Examples
+    /// ```
+    /// tiles::load_tiles(0, &FONT);
+    /// Window::map().write_text(1, 0, "SCORE", 0);
+    /// ```
+    pub fn write_text(&self, x: u8, y: u8, text: &str, first_tile: u8) {
+        for (i, c) in text.bytes().enumerate() {
+            let c = if c.is_ascii_graphic() { c } else { b' ' };
+            self.set_tile(
+                x.wrapping_add(i as u8),
+                y,
+                first_tile.wrapping_add(c - b' '),
+            );
+        }
+    }
+
+    /// Fill a rectangle with one tile.
+    pub fn fill(&self, x: u8, y: u8, width: u8, height: u8, tile: u8) {
+        for row in 0..height {
+            for column in 0..width {
+                self.set_tile(x.wrapping_add(column), y.wrapping_add(row), tile);
+            }
+        }
+    }
+
+    /// Fill the whole map with one tile.
+    pub fn clear(&self, tile: u8) {
+        self.fill(0, 0, MAP_SIZE, MAP_SIZE, tile);
+    }
+}
+
+#[cfg(any(feature = "color", doc))]
+mod color {
+    use crate::register::{self, field, flag, register_type, TileMapArea, Vbk};
+
+    use super::{TileMap, MAP_SIZE};
+
+    /// Selected VRAM bank, restored when dropped.
+    ///
+    /// Every function of [`crate::tiles`] accesses the selected bank. Don't
+    /// switch the VRAM bank in an interrupt handler without restoring it.
+    ///
+    /// # Examples
+    /// ```
+    /// {
+    ///     let _bank = VramBank::select(1);
+    ///     tiles::load_tiles(0, &BANK1_TILES);
+    /// }
+    /// // Bank 0 is selected again.
+    /// ```
+    pub struct VramBank {
+        previous: u8,
+    }
+
+    impl VramBank {
+        /// Select a VRAM bank, `0` or `1`.
+        ///
+        /// # Panics
+        ///
+        /// Panics if `bank` is greater than 1.
+        pub fn select(bank: u8) -> Self {
+            if bank > 1 {
+                panic!("VRAM bank outbounded");
+            }
+            let previous = Self::current();
+            register::VBK.write(Vbk::new().with_bank(bank));
+            VramBank { previous }
+        }
+
+        /// Currently selected VRAM bank.
+        pub fn current() -> u8 {
+            register::VBK.read().bank()
+        }
+    }
+
+    impl Drop for VramBank {
+        fn drop(&mut self) {
+            register::VBK.write(Vbk::new().with_bank(self.previous));
+        }
+    }
+
+    register_type!(
+        /// BG map attributes of a tile. (CGB mode only)
+        #[derive(Debug)]
+        BgAttributes
+    );
+
+    impl BgAttributes {
+        flag!(
+            /// Whether colors 1-3 of the tile are drawn over sprites.
+            priority, with_priority, set_priority, 7
+        );
+        flag!(
+            /// Whether the tile is vertically mirrored.
+            y_flip, with_y_flip, set_y_flip, 6
+        );
+        flag!(
+            /// Whether the tile is horizontally mirrored.
+            x_flip, with_x_flip, set_x_flip, 5
+        );
+        field!(
+            /// VRAM bank of the tile data.
+            vram_bank, with_vram_bank, set_vram_bank, 3, 0b1
+        );
+        field!(
+            /// BG palette, from `0` to `7`.
+            palette, with_palette, set_palette, 0, 0b111
+        );
+    }
+
+    /// Attributes of a tile map, in VRAM bank 1. (CGB mode only)
+    ///
+    /// Each attribute applies to the tile at the same coordinate of the
+    /// [`TileMap`] of the same area. Bank 1 is selected during each access.
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub struct AttributeMap {
+        map: TileMap,
+    }
+
+    impl AttributeMap {
+        /// Creates a handle of the attribute map of given area.
+        pub const fn new(area: TileMapArea) -> Self {
+            AttributeMap {
+                map: TileMap::new(area),
+            }
+        }
+
+        /// Set the attributes at given coordinate.
+        pub fn set_attributes(&self, x: u8, y: u8, attributes: BgAttributes) {
+            let _bank = VramBank::select(1);
+            self.map.set_tile(x, y, attributes.bits());
+        }
+
+        /// Get the attributes at given coordinate.
+        pub fn attributes(&self, x: u8, y: u8) -> BgAttributes {
+            let _bank = VramBank::select(1);
+            BgAttributes(self.map.tile(x, y))
+        }
+
+        /// Set a rectangle of attributes, given in row-major order.
+        ///
+        /// # Panics
+        ///
+        /// Panics if `attributes` is shorter than `width * height`.
+        pub fn set_rect(&self, x: u8, y: u8, width: u8, height: u8, attributes: &[BgAttributes]) {
+            if attributes.len() < width as usize * height as usize {
+                panic!("Attribute map data too short");
+            }
+            let _bank = VramBank::select(1);
+            for row in 0..height {
+                for column in 0..width {
+                    let attributes = attributes[row as usize * width as usize + column as usize];
+                    self.map.set_tile(
+                        x.wrapping_add(column),
+                        y.wrapping_add(row),
+                        attributes.bits(),
+                    );
+                }
+            }
+        }
+
+        /// Fill a rectangle with the same attributes.
+        pub fn fill(&self, x: u8, y: u8, width: u8, height: u8, attributes: BgAttributes) {
+            let _bank = VramBank::select(1);
+            self.map.fill(x, y, width, height, attributes.bits());
+        }
+
+        /// Fill the whole map with the same attributes.
+        pub fn clear(&self, attributes: BgAttributes) {
+            self.fill(0, 0, MAP_SIZE, MAP_SIZE, attributes);
+        }
+    }
+}
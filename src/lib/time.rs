@@ -1,26 +1,34 @@
 use core::ops::{Add, AddAssign, Sub, SubAssign};
 use core::time::Duration;
 
-use crate::{irq::Interrupt, mmio};
+use crate::register::{self, Tac};
+use crate::{
+    irq::{critical_section, Interrupt, InterruptKind},
+    mmio,
+};
+
+pub use crate::register::TimerClock;
 
 static mut ENABLE_TIMER: bool = false;
 static mut SYSTEM_TIMER: u32 = 0;
 
-unsafe extern "C" fn increase_system_timer() {
-    SYSTEM_TIMER += 1;
+/// `SYSTEM_TIMER` units per timer interrupt, so that the timer counts at the
+/// same rate in double speed mode, where TIMA is twice as fast.
+fn timer_step() -> u32 {
+    #[cfg(feature = "color")]
+    if crate::cpu::is_double_speed() {
+        return 1;
+    }
+    2
 }
 
-extern "C" {
-    fn halt();
+extern "C" fn increase_system_timer() {
+    // SAFETY: Only accessed with interrupts disabled, or read by `Instant::now`.
+    unsafe { SYSTEM_TIMER += timer_step() };
 }
 
-#[repr(u8)]
-#[derive(Clone, Copy)]
-pub enum TimerClock {
-    MCycle256 = 0x00, // 4096 Hz
-    MCycle4 = 0x01,   // 262144 Hz
-    MCycle16 = 0x10,  // 65536 Hz
-    MCycle64 = 0x11,  // 16384 Hz
+extern "C" {
+    fn halt();
 }
 
 /// A measurement of a monotonically nondecreasing clock.
@@ -28,29 +36,31 @@ pub enum TimerClock {
 ///
 /// Provide features similar to `std::time::Instant` using timer intterupt of GameBoy.
 ///
-/// You must enable timer interrupt with the [`Instant::init()`] method before use,
+/// You must register the timer interrupt returned by the [`Instant::init()`] method
+/// with [`Interrupt::register`] before use,
 /// and modifying timer interrupt related memory while enabling [`Instant`] will result in
 /// inaccurate time clock for [`Instant`]
 ///
+/// The clock keeps its rate in the double speed mode of the GameBoy Color,
+/// even across speed switches.
+///
 /// There is an error of around 180 seconds per hour (precision 5%).
 /// Therefore, it is not recommended to measure a long time with this feature.
-/// The RTC (Real Time Clock) feature, which can be used for long time measurements,
-/// will be implemented in the future.
+/// Use the real time clock of MBC3 cartridges ([`crate::rtc::RealTimeClock`])
+/// for long time measurements.
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Instant(u32);
 
 impl Instant {
-    // SYSTEM_TIMER increasing frequency.
-    pub const FREQUENCY: u32 = 4096 / 0xFF;
+    // SYSTEM_TIMER increasing frequency. The timer interrupt occurs at 16Hz
+    // (32Hz in double speed mode), and increases SYSTEM_TIMER by 2 (by 1).
+    pub const FREQUENCY: u32 = 4096 / 0xFF * 2;
 
     pub unsafe fn init() -> Interrupt {
-        // Make SYSTEM_TIMER increase in 16Hz (Slowest as possible)
+        // Make the timer interrupt occur in 16Hz (Slowest as possible)
         Self::enable_timer(TimerClock::MCycle256, 0);
         ENABLE_TIMER = true;
-        Interrupt {
-            kind: crate::irq::InterruptKind::Timer,
-            handler: increase_system_timer,
-        }
+        Interrupt::new(InterruptKind::Timer, increase_system_timer)
     }
 
     pub fn now() -> Self {
@@ -58,11 +68,12 @@ impl Instant {
             if !ENABLE_TIMER {
                 panic!("Instant is not initalized");
             }
-            return Instant(SYSTEM_TIMER);
+            // The timer interrupt can occur while reading the 32-bit counter.
+            return Instant(critical_section(|| SYSTEM_TIMER));
         }
     }
 
-    // TODO: handle double speed mode / SGB
+    // TODO: handle SGB
     pub fn checked_duration_since(&self, earlier: Instant) -> Option<Duration> {
         if self.0 < earlier.0 {
             None
@@ -96,24 +107,24 @@ impl Instant {
     }
 
     pub fn checked_add(&self, duration: Duration) -> Option<Instant> {
-        self.0
-            .checked_add(duration.as_millis() as u32 / 1000 * Self::FREQUENCY)
-            .map(Instant)
+        let ticks = (duration.as_millis() as u32).checked_mul(Self::FREQUENCY)? / 1000;
+        self.0.checked_add(ticks).map(Instant)
     }
 
     pub fn checked_sub(&self, duration: Duration) -> Option<Instant> {
-        self.0
-            .checked_sub(duration.as_millis() as u32 / 1000 * Self::FREQUENCY)
-            .map(Instant)
+        let ticks = (duration.as_millis() as u32).checked_mul(Self::FREQUENCY)? / 1000;
+        self.0.checked_sub(ticks).map(Instant)
     }
 
     fn enable_timer(clock: TimerClock, modulo: u8) {
         mmio::TMA.write(modulo);
-        mmio::TAC.write(0x4 | clock as u8);
+        register::TAC.write(Tac::new().with_enabled(true).with_clock(clock));
     }
 
     fn disable_timer() {
-        mmio::TAC.write(0);
+        register::TAC.apply(|tac| {
+            tac.set_enabled(false);
+        });
     }
 }
 
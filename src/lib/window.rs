@@ -0,0 +1,121 @@
+//! Window layer.
+//!
+//! The window is a second background layer drawn over the background. It is
+//! not scrolled with the background, and it covers the screen from its
+//! position to the bottom right corner, so it is usually used for a HUD or a
+//! status bar.
+//!
+//! The window shares the tile data area with the background, and it is only
+//! shown when the background is also enabled. (See [`Background::show`])
+//!
+//! [`Background::show`]: crate::background::Background::show
+//!
+//! # Examples
+//! ```
+//! // Status bar in the bottom two rows of the screen.
+//! Window::set_map_area(TileMapArea::Area9C00);
+//! Window::map().clear(0);
+//! Window::write_text(1, 0, "SCORE 00000", 0);
+//! Window::set_position(0, 128);
+//! Window::show();
+//! ```
+//!
+//! Documents are provided by [GB Pan Docs](https://gbdev.io/pandocs/Window.html)
+
+use crate::{
+    lcd, mmio,
+    register::{self, TileMapArea},
+    tiles::TileMap,
+};
+
+/// Offset of the WX register from the screen X coordinate.
+pub const WX_OFFSET: u8 = 7;
+
+/// Window layer of the screen.
+pub struct Window {
+    private: (),
+}
+
+impl Window {
+    /// Tile map used by the window, selected by LCDC.
+    pub fn map() -> TileMap {
+        TileMap::new(register::LCDC.read().window_map())
+    }
+
+    /// Select the tile map used by the window.
+    ///
+    /// Use the other tile map than the background, unless the window should
+    /// show the same tiles as the background.
+    pub fn set_map_area(area: TileMapArea) {
+        lcd::update_lcdc(|lcdc| {
+            lcdc.set_window_map(area);
+        });
+    }
+
+    /// Show the window.
+    pub fn show() {
+        lcd::update_lcdc(|lcdc| {
+            lcdc.set_window_enabled(true);
+        });
+    }
+
+    /// Hide the window.
+    pub fn hide() {
+        lcd::update_lcdc(|lcdc| {
+            lcdc.set_window_enabled(false);
+        });
+    }
+
+    /// Whether the window is shown.
+    pub fn is_shown() -> bool {
+        register::LCDC.read().window_enabled()
+    }
+
+    /// Move the top left corner of the window to the screen coordinate
+    /// `(x, y)`.
+    ///
+    /// The 7 pixels offset of the WX register is handled by this function.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `x` is greater than 158 or `y` is greater than 143. Hide the
+    /// window instead of moving it out of the screen.
+    pub fn set_position(x: u8, y: u8) {
+        if x > 158 || y > 143 {
+            panic!("Window position outbounded");
+        }
+        // SAFETY: WX is between 7 and 165, avoiding the glitches of 0 and 166.
+        unsafe {
+            mmio::WX.write(x + WX_OFFSET);
+            mmio::WY.write(y);
+        }
+    }
+
+    /// Screen coordinate `(x, y)` of the top left corner of the window.
+    ///
+    /// `x` is saturated to 0 if WX was set to less than 7 directly.
+    pub fn position() -> (u8, u8) {
+        (mmio::WX.read().saturating_sub(WX_OFFSET), mmio::WY.read())
+    }
+
+    /// Set a tile of the window tile map.
+    pub fn set_tile(x: u8, y: u8, tile: u8) {
+        Self::map().set_tile(x, y, tile);
+    }
+
+    /// Set a rectangle of the window tile map, given in row-major order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tiles` is shorter than `width * height`.
+    pub fn set_tiles(x: u8, y: u8, width: u8, height: u8, tiles: &[u8]) {
+        Self::map().set_tiles(x, y, width, height, tiles);
+    }
+
+    /// Write ASCII text into the window tile map.
+    ///
+    /// See [`TileMap::write_text`] for the font layout.
+    pub fn write_text(x: u8, y: u8, text: &str, first_tile: u8) {
+        Self::map().write_text(x, y, text, first_tile);
+    }
+}